@@ -0,0 +1,169 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+//! Benchmarks for the hot paths of the upb `Arena`.
+//!
+//! Run with `cargo bench --bench arena` from the packaged crate.
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use protobuf::__runtime::Arena;
+
+fn bench_new_and_drop(c: &mut Criterion) {
+    c.bench_function("arena_new_and_drop", |b| b.iter(|| drop(black_box(Arena::new()))));
+}
+
+// Each iteration gets a fresh arena, so the measurements do not drift as one
+// arena grows across millions of iterations. Creating and dropping the arenas
+// is not timed. Large copies use smaller batches to bound the memory held by
+// the arenas of a batch at once.
+fn batch_size(bytes: usize) -> BatchSize {
+    if bytes <= 1024 {
+        BatchSize::SmallInput
+    } else {
+        BatchSize::LargeInput
+    }
+}
+
+fn bench_copy_slice_in(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arena_copy_slice_in");
+    for size in [8, 64, 1024, 65536] {
+        let data = vec![0xABu8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter_batched_ref(
+                Arena::new,
+                |arena| {
+                    black_box(arena.copy_slice_in(black_box(data)));
+                },
+                batch_size(size),
+            );
+        });
+    }
+    group.finish();
+}
+
+// copy_slice_in allocates through a private pointer-returning path; this
+// compares it against copying through the public checked_alloc() slice, the
+// way copy_slice_in used to.
+fn bench_copy_slice_in_alloc_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arena_copy_slice_in_alloc_path");
+    for size in [8, 64] {
+        let data = vec![0xABu8; size];
+        group.bench_with_input(BenchmarkId::new("alloc_ptr", size), &data, |b, data| {
+            b.iter_batched_ref(
+                Arena::new,
+                |arena| {
+                    black_box(arena.copy_slice_in(black_box(data)));
+                },
+                BatchSize::SmallInput,
+            );
+        });
+        group.bench_with_input(BenchmarkId::new("checked_alloc", size), &data, |b, data| {
+            b.iter_batched_ref(
+                Arena::new,
+                |arena| {
+                    let data = black_box(data);
+                    let copy = arena.checked_alloc(data.len(), 1).map(|alloc| {
+                        let alloc = alloc.as_mut_ptr().cast::<u8>();
+                        // SAFETY: `alloc` is valid for `data.len()` bytes, which are written
+                        // before being read.
+                        unsafe {
+                            core::ptr::copy_nonoverlapping(data.as_ptr(), alloc, data.len());
+                            core::slice::from_raw_parts(alloc, data.len())
+                        }
+                    });
+                    black_box(copy);
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+// A 16 MiB copy gets a dedicated upb block, so it should match copying into a
+// fresh `Vec`, which pays for the same allocation and page faults.
+fn bench_copy_large_slice_in(c: &mut Criterion) {
+    const SIZE: usize = 16 << 20;
+    let data = vec![0xABu8; SIZE];
+    let mut group = c.benchmark_group("arena_copy_large_slice_in");
+    group.throughput(Throughput::Bytes(SIZE as u64));
+    group.sample_size(20);
+    group.bench_function("copy_slice_in", |b| {
+        b.iter_batched_ref(
+            Arena::new,
+            |arena| {
+                black_box(arena.copy_slice_in(black_box(&data)));
+            },
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("vec_to_vec", |b| {
+        b.iter_batched(|| (), |()| black_box(&data).to_vec(), BatchSize::PerIteration);
+    });
+    group.finish();
+}
+
+fn bench_copy_str_in(c: &mut Criterion) {
+    c.bench_function("arena_copy_str_in", |b| {
+        b.iter_batched_ref(
+            Arena::new,
+            |arena| {
+                black_box(arena.copy_str_in(black_box("a typical string field value")));
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+fn bench_copy_small_in(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arena_copy_u64");
+    group.bench_function("copy_in", |b| {
+        b.iter_batched_ref(
+            Arena::new,
+            |arena| {
+                black_box(arena.copy_in(black_box(&0xABu64)));
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("copy_small_in", |b| {
+        b.iter_batched_ref(
+            Arena::new,
+            |arena| {
+                black_box(arena.copy_small_in(black_box(&0xABu64)));
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+fn bench_fuse(c: &mut Criterion) {
+    c.bench_function("arena_fuse", |b| {
+        b.iter(|| {
+            let a = Arena::new();
+            let b = Arena::new();
+            a.fuse(&b);
+            black_box((a, b))
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_new_and_drop,
+    bench_copy_slice_in,
+    bench_copy_slice_in_alloc_path,
+    bench_copy_large_slice_in,
+    bench_copy_str_in,
+    bench_copy_small_in,
+    bench_fuse
+);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+//! Fuzzes copying arena-backed byte slices around.
+//!
+//! Run with `cargo fuzz run arena_bytes` from the packaged crate.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protobuf::__runtime::{ArbitraryArenaBytes, Arena};
+
+fuzz_target!(|input: ArbitraryArenaBytes| {
+    let arena = Arena::new();
+    arena.fuse(input.arena());
+    for slice in input.slices() {
+        assert_eq!(arena.copy_slice_in(slice).unwrap(), slice);
+        assert_eq!(arena.copy_slice_in_checked(slice).unwrap().0, slice);
+        assert!(arena.copy_verified_in(slice).unwrap().verify());
+    }
+});
@@ -0,0 +1,153 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::arena::UPB_MALLOC_ALIGN;
+use super::Arena;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ptr::{self, NonNull};
+use core::slice;
+
+/// A slice in a upb Arena which can be extended with more elements.
+///
+/// The slice keeps spare capacity after its elements, which grows
+/// geometrically, so appending is amortized O(1) per element. When the
+/// capacity runs out, the new room is allocated from the arena; if that lands
+/// right after the current capacity, which is the case while nothing else has
+/// been allocated from the arena in between, it is merged in and the slice
+/// grows in place. Otherwise it becomes the slice's new home and the elements
+/// are copied over.
+#[derive(Debug)]
+pub struct AppendableSlice<'a, T> {
+    arena: &'a Arena,
+    // `cap` elements are reserved at `base`, of which the first `len` are
+    // initialized and may be referenced by slices already handed out.
+    base: NonNull<T>,
+    len: usize,
+    cap: usize,
+    _phantom: PhantomData<&'a [T]>,
+}
+
+impl Arena {
+    /// Returns an empty slice which can be extended with
+    /// `AppendableSlice::push_all`.
+    pub fn appendable_slice<T: Copy>(&self) -> AppendableSlice<'_, T> {
+        // Zero-sized elements take no room, so any number of them fit.
+        let cap = if size_of::<T>() == 0 { usize::MAX } else { 0 };
+        AppendableSlice {
+            arena: self,
+            base: NonNull::dangling(),
+            len: 0,
+            cap,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Copy> AppendableSlice<'a, T> {
+    /// Appends `more` to the slice and returns the combined slice. Returns
+    /// None if the allocation failed, in which case the slice is unchanged.
+    pub fn push_all(&mut self, more: &[T]) -> Option<&'a [T]> {
+        let len = self.len.checked_add(more.len())?;
+        if len > self.cap {
+            self.grow(len)?;
+        }
+        // SAFETY:
+        // - `base` is valid for `cap >= len` elements, and the `more.len()` after the
+        //   first `self.len` are not referenced by anything yet.
+        // - `more` can not overlap them, since nothing else has been handed out.
+        unsafe {
+            ptr::copy_nonoverlapping(more.as_ptr(), self.base.as_ptr().add(self.len), more.len())
+        };
+        self.len = len;
+        Some(self.as_slice())
+    }
+
+    /// Makes room for at least `len` elements.
+    fn grow(&mut self, len: usize) -> Option<()> {
+        // Round so the capacity is a whole number of `UPB_MALLOC_ALIGN` chunks,
+        // which lets the next allocation land directly after it.
+        let step = UPB_MALLOC_ALIGN >> size_of::<T>().trailing_zeros().min(3);
+        let cap = len.max(self.cap.saturating_mul(2)).checked_next_multiple_of(step)?;
+        let size = size_of::<T>().checked_mul(cap)?;
+        let alloc = self.arena.checked_alloc(size, align_of::<T>())?;
+        let alloc = NonNull::from(alloc).cast::<T>();
+        let end = self.base.as_ptr() as usize + self.cap * size_of::<T>();
+        if self.cap > 0 && alloc.as_ptr() as usize == end {
+            // SAFETY: `alloc` is the most recent allocation, of `size` bytes, and only
+            // the room needed for `cap` elements in total is kept.
+            unsafe {
+                self.arena.shrink_last(alloc.cast(), size, (cap - self.cap) * size_of::<T>())
+            };
+        } else {
+            // SAFETY: `alloc` is a fresh allocation valid for `cap >= self.len`
+            // elements, and the first `self.len` elements of `base` are initialized.
+            unsafe { ptr::copy_nonoverlapping(self.base.as_ptr(), alloc.as_ptr(), self.len) };
+            self.base = alloc;
+        }
+        self.cap = cap;
+        Some(())
+    }
+
+    /// Returns the elements appended so far.
+    pub fn as_slice(&self) -> &'a [T] {
+        // SAFETY: the first `len` elements at `base` are initialized, are never
+        // written again, and live as long as the arena.
+        unsafe { slice::from_raw_parts(self.base.as_ptr(), self.len) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_push_all_in_three_batches() {
+        let arena = Arena::new();
+        let mut slice = arena.appendable_slice::<u64>();
+        let first = slice.push_all(&[1, 2]).unwrap();
+        slice.push_all(&[3]).unwrap();
+        slice.push_all(&[4, 5, 6]).unwrap();
+        assert_eq!(slice.as_slice(), &[1, 2, 3, 4, 5, 6]);
+        // Without canaries in between, every batch was adjacent to the last.
+        #[cfg(not(feature = "arena-canary"))]
+        assert_eq!(slice.as_slice().as_ptr(), first.as_ptr());
+        assert_eq!(first, &[1, 2]);
+    }
+
+    #[gtest]
+    fn test_push_all_after_other_allocation() {
+        let arena = Arena::new();
+        let mut slice = arena.appendable_slice::<u32>();
+        let first = slice.push_all(&[1, 2]).unwrap();
+        arena.copy_in(&0u64).unwrap();
+        slice.push_all(&[3, 4]).unwrap();
+        assert_eq!(slice.as_slice(), &[1, 2, 3, 4]);
+        assert_ne!(slice.as_slice().as_ptr(), first.as_ptr());
+        assert_eq!(first, &[1, 2]);
+    }
+
+    #[gtest]
+    fn test_push_all_grows_geometrically() {
+        let arena = Arena::new();
+        let mut slice = arena.appendable_slice::<u32>();
+        let mut moves = 0;
+        let mut base = ptr::null();
+        for i in 0..1000 {
+            // Keep the slice from ever growing in place.
+            arena.copy_in(&0u8).unwrap();
+            let combined = slice.push_all(&[i]).unwrap();
+            if combined.as_ptr() != base {
+                moves += 1;
+                base = combined.as_ptr();
+            }
+        }
+        assert_eq!(slice.as_slice(), (0..1000).collect::<Vec<_>>());
+        assert!(moves <= 10, "{moves}");
+    }
+}
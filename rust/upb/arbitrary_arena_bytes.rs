@@ -0,0 +1,66 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::{Arena, ArenaHandle};
+use arbitrary::{Arbitrary, Unstructured};
+
+/// Byte slices of fuzzer-chosen number and lengths, copied into an arena.
+///
+/// This gives fuzz targets a ready-made `Arbitrary` input for exercising
+/// arena code paths. `Arbitrary` has no way to be handed an existing arena,
+/// so the wrapper owns its `Arena` and keeps handles to the slices.
+#[derive(Debug)]
+pub struct ArbitraryArenaBytes {
+    arena: Arena,
+    slices: Vec<ArenaHandle<u8>>,
+}
+
+impl ArbitraryArenaBytes {
+    /// Returns the arena the slices were copied into.
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    /// Returns the slices, in the order they were generated.
+    pub fn slices(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.slices.iter().map(|&handle| self.arena.resolve(handle))
+    }
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryArenaBytes {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let arena = Arena::new();
+        let mut slices = Vec::new();
+        for bytes in u.arbitrary_iter::<&[u8]>()? {
+            let handle = arena.copy_slice_in_indexed(bytes?).expect("arena allocation failed");
+            slices.push(handle);
+        }
+        Ok(ArbitraryArenaBytes { arena, slices })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_arbitrary_from_fixed_seed() {
+        let seed: Vec<u8> = (0..64).map(|i| (i * 37 % 251) as u8).collect();
+        let input = ArbitraryArenaBytes::arbitrary(&mut Unstructured::new(&seed)).unwrap();
+        let expected: Vec<&[u8]> = Unstructured::new(&seed)
+            .arbitrary_iter::<&[u8]>()
+            .unwrap()
+            .collect::<arbitrary::Result<_>>()
+            .unwrap();
+        let slices: Vec<&[u8]> = input.slices().collect();
+        assert_eq!(slices, expected);
+        for (slice, original) in slices.iter().zip(&expected) {
+            assert_ne!(slice.as_ptr(), original.as_ptr());
+        }
+    }
+}
@@ -6,20 +6,352 @@
 // https://developers.google.com/open-source/licenses/bsd
 
 use super::opaque_pointee::opaque_pointee;
-use core::cell::UnsafeCell;
+use core::any::type_name;
+use core::cell::{Cell, OnceCell, RefCell, UnsafeCell};
+use core::cmp::Ordering;
+use core::ffi::c_char;
+use core::fmt;
+use core::iter::Rev;
 use core::marker::PhantomData;
-use core::mem::{align_of, align_of_val, size_of_val, MaybeUninit};
+use core::mem::{align_of, align_of_val, needs_drop, size_of, size_of_val, MaybeUninit};
+use core::pin::Pin;
 use core::ptr::{self, NonNull};
 use core::slice;
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, AtomicUsize};
+use std::collections::BTreeMap;
+#[cfg(feature = "arena-provenance")]
+use std::panic::Location;
+use std::sync::Mutex;
 
 opaque_pointee!(upb_Arena);
 pub type RawArena = NonNull<upb_Arena>;
 
+/// ABI compatible struct with upb_alloc.
+///
+/// See `upb/mem/alloc.h`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct upb_alloc {
+    /// A combined `malloc()`/`free()` function, see `upb_alloc_func`.
+    pub func: unsafe extern "C" fn(
+        alloc: *mut upb_alloc,
+        ptr: *mut u8,
+        oldsize: usize,
+        size: usize,
+    ) -> *mut u8,
+}
+
+/// A `upb_alloc` which serves all of its allocations out of an arena.
+///
+/// `alloc` must be the first field so that a `*mut upb_alloc` handed to upb
+/// can be cast back to a `*mut ArenaAlloc`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ArenaAlloc {
+    alloc: upb_alloc,
+    arena: RawArena,
+}
+
+/// # Safety
+/// - `alloc` must point to the `alloc` field of a live `ArenaAlloc`, and the
+///   `Arena` it was made from must not have been dropped.
+unsafe extern "C" fn arena_alloc_func(
+    alloc: *mut upb_alloc,
+    ptr: *mut u8,
+    oldsize: usize,
+    size: usize,
+) -> *mut u8 {
+    if size == 0 {
+        // The memory is reclaimed when the arena is freed, so `free()` is a
+        // no-op.
+        return ptr::null_mut();
+    }
+    // SAFETY:
+    // - `alloc` is the first field of a live `#[repr(C)]` `ArenaAlloc`, as promised
+    //   by the caller.
+    // - `ArenaAlloc::arena` is a valid arena, since the caller promises its `Arena`
+    //   has not been dropped.
+    unsafe {
+        let arena = (*alloc.cast::<ArenaAlloc>()).arena;
+        upb_Arena_Realloc(arena, ptr, oldsize, size)
+    }
+}
+
 /// See `upb/port/def.inc`.
-const UPB_MALLOC_ALIGN: usize = 8;
+pub(crate) const UPB_MALLOC_ALIGN: usize = 8;
 const _CHECK_UPB_MALLOC_ALIGN_AT_LEAST_POINTER_ALIGNED: () =
     assert!(UPB_MALLOC_ALIGN >= align_of::<*const ()>());
 
+struct AssertAlignSupported<const ALIGN: usize>;
+
+impl<const ALIGN: usize> AssertAlignSupported<ALIGN> {
+    const OK: () = assert!(
+        ALIGN.is_power_of_two() && ALIGN <= UPB_MALLOC_ALIGN,
+        "alignment must be a power of two no larger than UPB_MALLOC_ALIGN"
+    );
+}
+
+/// Returns the bytes of `value`, for example of a value copied in with
+/// `Arena::copy_in`.
+///
+/// The bytes are `MaybeUninit` because any padding in `T` is uninitialized;
+/// `copy_in` copies `T` as a typed value rather than as bytes, which leaves the
+/// padding of the arena copy uninitialized too. Reading padding as `u8` is
+/// undefined behavior (and is flagged by Miri and MSan), so callers must only
+/// `assume_init` the bytes they know to be part of a field.
+pub fn uninit_bytes_of<T: Copy>(value: &T) -> &[MaybeUninit<u8>] {
+    // SAFETY:
+    // - `value` is valid for reads of `size_of::<T>()` bytes.
+    // - `MaybeUninit<u8>` has no validity requirements and no alignment.
+    unsafe { slice::from_raw_parts((value as *const T).cast(), size_of::<T>()) }
+}
+
+/// Panics if the memory of `a` and `b` overlaps, for validating that two
+/// arena slices do not alias. Empty slices never overlap.
+///
+/// The check only runs in builds with debug assertions; otherwise this is a
+/// no-op.
+#[inline]
+pub fn assert_no_alias<T>(a: &[T], b: &[T]) {
+    if cfg!(debug_assertions) && size_of_val(a) != 0 && size_of_val(b) != 0 {
+        let (a, b) = (a.as_ptr_range(), b.as_ptr_range());
+        assert!(a.end <= b.start || b.end <= a.start, "slices alias: {a:?} and {b:?}");
+    }
+}
+
+/// The byte fresh allocations are filled with in debug builds, or'ed with
+/// `POISON_ENABLED`; 0 while poisoning is off.
+static POISON_PATTERN: AtomicU16 = AtomicU16::new(0);
+const POISON_ENABLED: u16 = 0x100;
+
+/// Makes every later allocation from any arena start out filled with `byte`
+/// (e.g. 0xAB), so that accidental reads of memory which was never written
+/// show up as a recognizable pattern.
+///
+/// This only has an effect in builds with debug assertions, and none on an
+/// arena-deterministic build, which zeroes allocations after poisoning them.
+///
+/// The setting is process-wide, so it also poisons allocations made by other
+/// threads, such as tests running in parallel; tests which set it should run
+/// in their own process. `clear_poison_pattern` turns it back off.
+pub fn set_poison_pattern(byte: u8) {
+    POISON_PATTERN.store(POISON_ENABLED | u16::from(byte), core::sync::atomic::Ordering::Relaxed);
+}
+
+/// Stops filling fresh allocations with a poison pattern, undoing
+/// `set_poison_pattern`.
+pub fn clear_poison_pattern() {
+    POISON_PATTERN.store(0, core::sync::atomic::Ordering::Relaxed);
+}
+
+/// Returns the byte set by `set_poison_pattern`, or None while poisoning is
+/// off.
+pub fn poison_pattern() -> Option<u8> {
+    let pattern = POISON_PATTERN.load(core::sync::atomic::Ordering::Relaxed);
+    (pattern & POISON_ENABLED != 0).then_some(pattern as u8)
+}
+
+#[cfg(feature = "arena-shadow")]
+std::thread_local! {
+    static SHADOW_LOG: RefCell<Vec<Vec<MaybeUninit<u8>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// With the arena-shadow feature, appends a mirror of the bytes of `copy` to
+/// this thread's shadow log; otherwise does nothing.
+#[inline]
+fn shadow_record<T: Copy>(copy: &[T]) {
+    #[cfg(feature = "arena-shadow")]
+    {
+        // SAFETY: `copy` is valid for reads of `size_of_val(copy)` bytes, and
+        // `MaybeUninit<u8>` has no validity requirements, so padding is fine.
+        let bytes = unsafe {
+            slice::from_raw_parts(copy.as_ptr().cast::<MaybeUninit<u8>>(), size_of_val(copy))
+        };
+        SHADOW_LOG.with(|log| log.borrow_mut().push(bytes.to_vec()));
+    }
+    #[cfg(not(feature = "arena-shadow"))]
+    let _ = copy;
+}
+
+/// Returns the bytes copied into arenas on this thread since the last call,
+/// one entry per copy in the order they were made, and clears the log.
+///
+/// Every function of `Arena` which copies data in records the bytes it
+/// wrote, as they were just after the copy: functions which then rearrange the
+/// copy in place, such as `copy_sorted_in`, record the unsorted bytes. `alloc`
+/// and the other raw allocation functions hand out uninitialized memory, so
+/// they record nothing.
+///
+/// As with `uninit_bytes_of`, padding bytes of the copied values are
+/// uninitialized, so only the bytes known to be initialized may be
+/// `assume_init`ed. The log is never trimmed, so it holds a heap copy of
+/// everything copied in until it is taken: call this regularly when
+/// shadowing long-running work.
+#[cfg(feature = "arena-shadow")]
+pub fn take_shadow_log() -> Vec<Vec<MaybeUninit<u8>>> {
+    SHADOW_LOG.with(|log| log.take())
+}
+
+/// Returns the `Arena::allocation_histogram` bucket for an allocation of
+/// `size` bytes.
+#[cfg(feature = "arena-tracking")]
+fn histogram_bucket(size: usize) -> usize {
+    size.next_power_of_two().trailing_zeros().min(31) as usize
+}
+
+/// Compile time assertion that a type has no drop glue, since the arena never
+/// runs destructors.
+pub(crate) struct AssertNoDropGlue<T>(PhantomData<T>);
+
+impl<T> AssertNoDropGlue<T> {
+    pub(crate) const OK: () = assert!(!needs_drop::<T>(), "type must not have drop glue");
+}
+
+/// Compile time assertion that a type is small enough for `copy_small_in`.
+struct AssertSmall<T>(PhantomData<T>);
+
+impl<T> AssertSmall<T> {
+    const OK: () = assert!(size_of::<T>() <= 64, "type is too large for copy_small_in");
+}
+
+/// An error returned when data to be copied into an arena is longer than the
+/// caller-provided limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthExceeded {
+    /// Length of the rejected data.
+    pub len: usize,
+    /// The limit it exceeded.
+    pub max_len: usize,
+}
+
+impl std::error::Error for LengthExceeded {}
+
+impl fmt::Display for LengthExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "length {} exceeds the maximum of {}", self.len, self.max_len)
+    }
+}
+
+/// An error returned by `Arena::try_copy_slice_in` and
+/// `Arena::copy_str_in_bounded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaError {
+    /// The element type needs a larger alignment than `UPB_MALLOC_ALIGN`.
+    AlignmentTooLarge,
+    /// The data was longer than the caller-provided limit.
+    LengthExceeded(LengthExceeded),
+    /// The arena could not allocate the memory.
+    OutOfMemory,
+}
+
+impl std::error::Error for ArenaError {}
+
+impl fmt::Display for ArenaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArenaError::AlignmentTooLarge => {
+                write!(f, "alignment exceeds UPB_MALLOC_ALIGN {UPB_MALLOC_ALIGN}")
+            }
+            ArenaError::LengthExceeded(e) => e.fmt(f),
+            ArenaError::OutOfMemory => write!(f, "arena allocation failed"),
+        }
+    }
+}
+
+/// Guard bytes written around allocations with the arena-canary feature.
+#[cfg(feature = "arena-canary")]
+const CANARY: [u8; 8] = *b"\xCA\xFE\xBA\xBE\xDE\xAD\xBE\xEF";
+
+/// Lookup table for the reflected CRC-32 (IEEE 802.3) polynomial.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Returns the CRC-32 (IEEE) of `data`.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (crc >> 8) ^ CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize]
+    })
+}
+
+/// A callback fired the first time an arena's `space_allocated` exceeds
+/// `bytes`, see `Arena::new_with_threshold`.
+#[cfg(feature = "arena-pressure")]
+struct PressureThreshold {
+    bytes: usize,
+    on_exceed: Box<dyn Fn(usize) + Send>,
+    fired: Cell<bool>,
+}
+
+#[cfg(feature = "arena-pressure")]
+impl fmt::Debug for PressureThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PressureThreshold")
+            .field("bytes", &self.bytes)
+            .field("fired", &self.fired)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Callbacks run when an `Arena` handle is dropped, see
+/// `Arena::on_handle_drop`.
+#[derive(Default)]
+struct DropHooks(RefCell<Vec<Box<dyn FnOnce() + Send>>>);
+
+impl fmt::Debug for DropHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DropHooks").finish_non_exhaustive()
+    }
+}
+
+/// The ids handed out by `Arena::handle_id`, keyed by the address of the raw
+/// arena. Entries are removed when their `Arena` is dropped.
+static HANDLE_IDS: Mutex<BTreeMap<usize, u64>> = Mutex::new(BTreeMap::new());
+
+/// The number of entries in `HANDLE_IDS`, so that dropping an arena only takes
+/// the lock if some live arena has an id.
+static HANDLE_ID_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// An error returned when a string to be copied into an arena as a C string
+/// contains a NUL byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteriorNul {
+    /// Index of the rejected string.
+    pub index: usize,
+    /// Byte position of the NUL within the string.
+    pub position: usize,
+}
+
+impl std::error::Error for InteriorNul {}
+
+impl fmt::Display for InteriorNul {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "string {} has an interior NUL byte at position {}", self.index, self.position)
+    }
+}
+
+/// Memory usage of an `Arena`, see `Arena::stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Total bytes in the blocks owned by the arena and any arenas fused to
+    /// it.
+    pub space_allocated: usize,
+    /// Number of arenas in the fused group, including the arena itself.
+    pub fused_count: usize,
+}
+
 /// A wrapper over a `upb_Arena`.
 ///
 /// This is not a safe wrapper per se, because the allocation functions still
@@ -35,11 +367,36 @@ const _CHECK_UPB_MALLOC_ALIGN_AT_LEAST_POINTER_ALIGNED: () =
 /// mutability (&self rather than &mut self receivers) See https://doc.rust-lang.org/nomicon/lifetime-mismatch.html and
 /// https://blog.reverberate.org/2021/12/19/arenas-and-rust.html, and the
 /// 'known problems' section of https://rust-lang.github.io/rust-clippy/master/index.html#/mut_from_ref.
+///
+/// There is no shared read-only (frozen) form of an arena. To read arena data
+/// from another thread, move the `Arena` there: the `Send` handoff (a thread
+/// spawn, a channel, a mutex) synchronizes, so writes made before the move are
+/// visible after it without any additional fence.
 #[derive(Debug)]
 pub struct Arena {
     // Safety invariant: this must always be a valid arena
     raw: RawArena,
     _not_sync: PhantomData<UnsafeCell<()>>,
+    #[cfg(feature = "arena-pressure")]
+    pressure: Option<Box<PressureThreshold>>,
+    // Allocated by the first `on_handle_drop`, so arenas without hooks pay only
+    // a null check on drop.
+    drop_hooks: OnceCell<Box<DropHooks>>,
+    #[cfg(feature = "arena-canary")]
+    canaries: RefCell<Vec<(NonNull<u8>, usize)>>,
+    #[cfg(feature = "arena-deterministic")]
+    allocations: RefCell<Vec<(NonNull<u8>, usize)>>,
+    #[cfg(feature = "arena-provenance")]
+    last_alloc_site: Cell<Option<&'static Location<'static>>>,
+    // Regions copied in by `copy_slice_in_readonly`, as a map from the start
+    // address to the end address. Regions never overlap, so the one which could
+    // overlap a range is the last starting before the range's end.
+    #[cfg(debug_assertions)]
+    readonly: RefCell<BTreeMap<usize, usize>>,
+    #[cfg(feature = "arena-tracking")]
+    alignment_waste: Cell<usize>,
+    #[cfg(feature = "arena-tracking")]
+    allocation_histogram: RefCell<[usize; 32]>,
 }
 
 // SAFETY: `Arena` uniquely holds the underlying RawArena and has no
@@ -61,15 +418,94 @@ impl Arena {
         //   call; if it returned a non-null pointer, it is a valid arena.
         unsafe {
             let Some(raw) = upb_Arena_New() else { arena_new_failed() };
-            Self { raw, _not_sync: PhantomData }
+            Self {
+                raw,
+                _not_sync: PhantomData,
+                #[cfg(feature = "arena-pressure")]
+                pressure: None,
+                drop_hooks: OnceCell::new(),
+                #[cfg(feature = "arena-canary")]
+                canaries: RefCell::new(Vec::new()),
+                #[cfg(feature = "arena-deterministic")]
+                allocations: RefCell::new(Vec::new()),
+                #[cfg(feature = "arena-provenance")]
+                last_alloc_site: Cell::new(None),
+                #[cfg(debug_assertions)]
+                readonly: RefCell::new(BTreeMap::new()),
+                #[cfg(feature = "arena-tracking")]
+                alignment_waste: Cell::new(0),
+                #[cfg(feature = "arena-tracking")]
+                allocation_histogram: RefCell::new([0; 32]),
+            }
+        }
+    }
+
+    /// Allocates a fresh arena with a block of at least `bytes` bytes whose
+    /// pages have already been written to.
+    ///
+    /// This moves the cost of page faults up front, trading a slower
+    /// construction for more predictable latency on the first allocations.
+    /// The prefaulted memory is handed back to the arena and is reused by
+    /// later allocations.
+    pub fn new_prefaulted(bytes: usize) -> Arena {
+        const PAGE_SIZE: usize = 4096;
+        let arena = Arena::new();
+        let Some(block) = arena.alloc_ptr(bytes, 1) else {
+            return arena;
+        };
+        for offset in (0..bytes).step_by(PAGE_SIZE) {
+            // SAFETY: `block` is valid for `bytes` bytes, so for `offset`. The write
+            // is volatile so it is not optimized away.
+            unsafe { ptr::write_volatile(block.as_ptr().add(offset), 0) };
         }
+        // SAFETY: `block` is the most recent allocation of `bytes` bytes from
+        // `arena` and is not referred to again.
+        unsafe { arena.shrink_last(block, bytes, 0) };
+        arena
+    }
+
+    /// Allocates a fresh arena which calls `on_exceed` with its
+    /// `space_allocated` the first time that exceeds `bytes`.
+    ///
+    /// This lets callers apply backpressure on memory growth. The check runs
+    /// after each allocation from this arena, and the callback fires at most
+    /// once. `space_allocated` counts whole blocks, including those of arenas
+    /// fused to this one, so the callback fires when the block that crosses
+    /// `bytes` is added rather than at the exact allocation that fills it.
+    ///
+    /// Only available with the arena-pressure feature, so that arenas built
+    /// without it carry no threshold and check none on each allocation.
+    #[cfg(feature = "arena-pressure")]
+    pub fn new_with_threshold(bytes: usize, on_exceed: Box<dyn Fn(usize) + Send>) -> Arena {
+        let mut arena = Arena::new();
+        arena.pressure =
+            Some(Box::new(PressureThreshold { bytes, on_exceed, fired: Cell::new(false) }));
+        arena
     }
 
     /// # Safety
     /// - The `raw_arena` must point to a valid arena.
     /// - The caller must ensure that the Arena's destructor does not run.
     pub unsafe fn from_raw(raw_arena: RawArena) -> Self {
-        Arena { raw: raw_arena, _not_sync: PhantomData }
+        Arena {
+            raw: raw_arena,
+            _not_sync: PhantomData,
+            #[cfg(feature = "arena-pressure")]
+            pressure: None,
+            drop_hooks: OnceCell::new(),
+            #[cfg(feature = "arena-canary")]
+            canaries: RefCell::new(Vec::new()),
+            #[cfg(feature = "arena-deterministic")]
+            allocations: RefCell::new(Vec::new()),
+            #[cfg(feature = "arena-provenance")]
+            last_alloc_site: Cell::new(None),
+            #[cfg(debug_assertions)]
+            readonly: RefCell::new(BTreeMap::new()),
+            #[cfg(feature = "arena-tracking")]
+            alignment_waste: Cell::new(0),
+            #[cfg(feature = "arena-tracking")]
+            allocation_histogram: RefCell::new([0; 32]),
+        }
     }
 
     /// Returns the raw, UPB-managed pointer to the arena.
@@ -78,6 +514,108 @@ impl Arena {
         self.raw
     }
 
+    /// Returns the caller of the most recent allocation from this arena, or
+    /// None if nothing has been allocated yet.
+    ///
+    /// The location is recorded through `#[track_caller]` on the primary
+    /// allocation functions; allocations made by other functions report a
+    /// location inside this crate. This is meant for attributing arena growth
+    /// to call sites while debugging.
+    #[cfg(feature = "arena-provenance")]
+    pub fn last_alloc_site(&self) -> Option<&'static Location<'static>> {
+        self.last_alloc_site.get()
+    }
+
+    /// Returns an id which is unique among all `Arena`s created by this
+    /// process, unlike the address of the raw arena which upb may reuse after
+    /// the arena is freed.
+    ///
+    /// Ids live in `HANDLE_IDS` rather than in the `Arena`, so arenas which
+    /// never ask for one pay nothing for them. The entry of an arena whose
+    /// destructor is skipped (see `from_raw`) is never removed.
+    pub(crate) fn handle_id(&self) -> u64 {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let mut ids = HANDLE_IDS.lock().unwrap();
+        *ids.entry(self.raw.as_ptr() as usize).or_insert_with(|| {
+            HANDLE_ID_COUNT.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+            NEXT_ID.fetch_add(1, core::sync::atomic::Ordering::Relaxed)
+        })
+    }
+
+    /// Removes this arena's entry from `HANDLE_IDS`, if it has one, so a raw
+    /// arena reused by upb is given a fresh id.
+    #[cold]
+    fn forget_handle_id(&self) {
+        if HANDLE_IDS.lock().unwrap().remove(&(self.raw.as_ptr() as usize)).is_some() {
+            HANDLE_ID_COUNT.fetch_sub(1, core::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the total number of bytes in the blocks owned by this arena
+    /// and any arenas fused to it.
+    ///
+    /// This grows a block at a time, so it is an upper bound on the bytes
+    /// actually handed out by the allocation functions.
+    pub fn space_allocated(&self) -> usize {
+        // SAFETY: `self.raw` is a valid UPB arena, and `fused_count` may be null.
+        unsafe { upb_Arena_SpaceAllocated(self.raw, ptr::null_mut()) }
+    }
+
+    /// Returns the memory usage of this arena and any arenas fused to it.
+    pub fn stats(&self) -> ArenaStats {
+        let mut fused_count = 0;
+        // SAFETY: `self.raw` is a valid UPB arena and `fused_count` is writable.
+        let space_allocated = unsafe { upb_Arena_SpaceAllocated(self.raw, &mut fused_count) };
+        ArenaStats { space_allocated, fused_count }
+    }
+
+    /// Returns a human-readable summary of this arena's memory, for
+    /// diagnosing fragmentation.
+    ///
+    /// upb does not expose its block list, so this reports the totals from
+    /// `stats()` rather than a per-block breakdown.
+    pub fn dump_layout(&self) -> String {
+        let ArenaStats { space_allocated, fused_count } = self.stats();
+        format!(
+            "upb_Arena {:p}: {space_allocated} bytes allocated in blocks across {fused_count} \
+             fused arena(s)",
+            self.raw.as_ptr()
+        )
+    }
+
+    /// Combines the stats of several arenas into one report, summing
+    /// `space_allocated` and taking the largest `fused_count`.
+    ///
+    /// Arenas which are fused to each other each report the whole fused
+    /// group, so passing more than one member of a group counts it repeatedly.
+    pub fn merge_stats<'a>(arenas: impl IntoIterator<Item = &'a Arena>) -> ArenaStats {
+        arenas.into_iter().map(Arena::stats).fold(ArenaStats::default(), |acc, stats| ArenaStats {
+            space_allocated: acc.space_allocated + stats.space_allocated,
+            fused_count: acc.fused_count.max(stats.fused_count),
+        })
+    }
+
+    /// Returns a `upb_alloc` which allocates out of this arena, for upb APIs
+    /// that take a raw allocator rather than a `upb_Arena*`.
+    ///
+    /// The returned pointer is valid until this `Arena` is dropped, even if it
+    /// is fused: the other arenas keep its memory alive, but a dropped arena
+    /// can not be allocated from. Frees made through the allocator are no-ops;
+    /// the memory is reclaimed when the arena is freed.
+    ///
+    /// Each call leaks one small allocator object into the arena, which is
+    /// not reclaimed until the arena is freed, so callers should reuse the
+    /// returned pointer rather than calling this repeatedly.
+    pub fn as_upb_alloc(&self) -> *mut upb_alloc {
+        let alloc = ArenaAlloc { alloc: upb_alloc { func: arena_alloc_func }, arena: self.raw };
+        let Some(alloc) = self.copy_in(&alloc) else {
+            return ptr::null_mut();
+        };
+        // The upb_alloc is never mutated by upb, the `*mut` is only for API
+        // compatibility with `upb_alloc*`.
+        (alloc as *const ArenaAlloc).cast_mut().cast()
+    }
+
     /// Allocates some memory on the arena. Returns None if the allocation
     /// failed.
     ///
@@ -86,16 +624,16 @@ impl Arena {
     /// - `align` must be less than `UPB_MALLOC_ALIGN`.
     #[allow(clippy::mut_from_ref)]
     #[inline]
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
     pub unsafe fn alloc(&self, size: usize, align: usize) -> Option<&mut [MaybeUninit<u8>]> {
         debug_assert!(align <= UPB_MALLOC_ALIGN);
-        // SAFETY: `self.raw` is a valid UPB arena
-        let ptr = unsafe { upb_Arena_Malloc(self.raw, size) };
+        let ptr = self.malloc(size);
 
         if ptr.is_null() {
             None
         } else {
             // SAFETY:
-            // - `upb_Arena_Malloc` promises that if the return pointer is non-null, it is
+            // - `malloc` promises that if the return pointer is non-null, it is
             //   dereferencable for `size` bytes and has an alignment of `UPB_MALLOC_ALIGN`
             //   until the arena is destroyed.
             // - `[MaybeUninit<u8>]` has no alignment requirement, and `ptr` is aligned to a
@@ -107,128 +645,2336 @@ impl Arena {
     /// Same as alloc() but panics if `align > UPB_MALLOC_ALIGN`.
     #[allow(clippy::mut_from_ref)]
     #[inline]
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
     pub fn checked_alloc(&self, size: usize, align: usize) -> Option<&mut [MaybeUninit<u8>]> {
         assert!(align <= UPB_MALLOC_ALIGN);
         // SAFETY: align <= UPB_MALLOC_ALIGN asserted.
         unsafe { self.alloc(size, align) }
     }
 
-    /// Copies the T into this arena and returns a pointer to the T data inside
-    /// the arena. Returns None if the allocation failed.
-    pub fn copy_in<'a, T: Copy>(&'a self, data: &T) -> Option<&'a T> {
-        let size = size_of_val(data);
-        let align = align_of_val(data);
+    /// Same as checked_alloc() but returns the pointer directly, without
+    /// constructing an intermediate slice.
+    ///
+    /// If non-null, the returned pointer is dereferencable for `size` bytes and
+    /// has an alignment of `UPB_MALLOC_ALIGN` until the arena is destroyed.
+    #[inline]
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    fn alloc_ptr(&self, size: usize, align: usize) -> Option<NonNull<u8>> {
+        assert!(align <= UPB_MALLOC_ALIGN);
+        NonNull::new(self.malloc(size))
+    }
 
-        self.checked_alloc(size, align).map(|alloc| {
-            // SAFETY:
-            // - alloc is valid for `size` bytes and is the uninit bytes are written to not
-            //   read from until written.
-            // - T is copy so copying the bytes of the value is sound.
-            unsafe {
-                let alloc = alloc.as_mut_ptr().cast::<MaybeUninit<T>>();
-                &*(*alloc).write(*data)
-            }
-        })
+    /// Panics if `T` is over-aligned, naming `op` and `T` in the message.
+    #[inline]
+    #[track_caller]
+    fn assert_align_supported<T>(op: &str) {
+        let align = align_of::<T>();
+        if align > UPB_MALLOC_ALIGN {
+            panic!(
+                "{op}::<{}>: align {align} exceeds UPB_MALLOC_ALIGN {UPB_MALLOC_ALIGN}",
+                type_name::<T>()
+            );
+        }
     }
 
-    /// Copies the str into this arena and returns a pointer to the T data
-    /// inside the arena. Returns None if the allocation failed.
-    pub fn copy_str_in<'a>(&'a self, s: &str) -> Option<&'a str> {
-        self.copy_slice_in(s.as_bytes()).map(|copied_bytes| {
-            // SAFETY: `copied_bytes` has same contents as `s` and so must meet &str
-            // criteria.
-            unsafe { core::str::from_utf8_unchecked(copied_bytes) }
-        })
+    /// In debug builds, panics if the `size` bytes at `ptr` overlap a region
+    /// copied in by `copy_slice_in_readonly`.
+    #[inline]
+    #[track_caller]
+    fn debug_assert_not_readonly(&self, ptr: *const u8, size: usize) {
+        #[cfg(debug_assertions)]
+        if size != 0 {
+            let (start, end) = (ptr as usize, ptr as usize + size);
+            if let Some((&region, &region_end)) = self.readonly.borrow().range(..end).next_back() {
+                assert!(
+                    region_end <= start,
+                    "released tail at {start:#x}..{end:#x} overlaps read-only region at \
+                     {region:#x}..{region_end:#x}"
+                );
+            }
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = (ptr, size);
     }
 
-    /// Copies the slice into this arena and returns a pointer to the T data
-    /// inside the arena. Returns None if the allocation failed.
-    pub fn copy_slice_in<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a [T]> {
-        let size = size_of_val(data);
-        let align = align_of_val(data);
-        self.checked_alloc(size, align).map(|alloc| {
-            let alloc: *mut T = alloc.as_mut_ptr().cast();
-            // SAFETY:
-            // - uninit_alloc is valid for `layout.len()` bytes and is the uninit bytes are
-            //   written to not read from until written.
-            // - T is copy so copying the bytes of the values is sound.
-            unsafe {
-                ptr::copy_nonoverlapping(data.as_ptr(), alloc, data.len());
-                slice::from_raw_parts(alloc, data.len())
+    /// Allocates `size` bytes with `upb_Arena_Malloc`, with the same guarantees
+    /// on the returned pointer.
+    ///
+    /// With the arena-canary feature, the allocation is padded with canaries on
+    /// both sides which `verify_canaries` checks.
+    #[inline]
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    fn malloc(&self, size: usize) -> *mut u8 {
+        #[cfg(not(feature = "arena-canary"))]
+        // SAFETY: `self.raw` is a valid UPB arena
+        let ptr = unsafe { upb_Arena_Malloc(self.raw, size) };
+        #[cfg(feature = "arena-canary")]
+        let ptr = match size.checked_add(2 * CANARY.len()) {
+            // SAFETY: `self.raw` is a valid UPB arena
+            Some(padded) => match NonNull::new(unsafe { upb_Arena_Malloc(self.raw, padded) }) {
+                Some(base) => {
+                    // SAFETY: `base` is valid for `size` bytes plus a canary on each side,
+                    // and `UPB_MALLOC_ALIGN` is the canary length so alignment is kept.
+                    unsafe {
+                        base.as_ptr().cast::<[u8; 8]>().write(CANARY);
+                        let ptr = base.as_ptr().add(CANARY.len());
+                        ptr.add(size).cast::<[u8; 8]>().write_unaligned(CANARY);
+                        self.canaries.borrow_mut().push((NonNull::new_unchecked(ptr), size));
+                        ptr
+                    }
+                }
+                None => ptr::null_mut(),
+            },
+            None => ptr::null_mut(),
+        };
+        #[cfg(debug_assertions)]
+        if !ptr.is_null() {
+            let pattern = POISON_PATTERN.load(core::sync::atomic::Ordering::Relaxed);
+            if pattern & POISON_ENABLED != 0 {
+                // SAFETY: `ptr` is valid for writes of `size` bytes.
+                unsafe { ptr.write_bytes(pattern as u8, size) };
             }
-        })
+        }
+        #[cfg(feature = "arena-deterministic")]
+        if let Some(ptr) = NonNull::new(ptr) {
+            // SAFETY: `ptr` is valid for writes of `size` bytes.
+            unsafe { ptr.as_ptr().write_bytes(0, size) };
+            self.allocations.borrow_mut().push((ptr, size));
+        }
+        #[cfg(feature = "arena-tracking")]
+        if !ptr.is_null() {
+            self.allocation_histogram.borrow_mut()[histogram_bucket(size)] += 1;
+        }
+        self.after_alloc(if ptr.is_null() { 0 } else { size });
+        ptr
     }
 
-    /// Fuse two arenas so they share the same lifetime.
+    /// Returns the contents of every allocation made through malloc(), in
+    /// allocation order, each prefixed by its size as a little-endian `u64`.
     ///
-    /// `fuse` will make it so that the memory allocated by `self` or `other` is
-    /// guaranteed to last until both `self` and `other` have been dropped.
-    /// The pointers returned by `Arena::alloc` will continue to be valid so
-    /// long as either `self` or `other` has not been dropped.
-    pub fn fuse(&self, other: &Arena) {
-        // SAFETY: `self.raw()` and `other.raw()` are both valid UPB arenas.
-        let success = unsafe { upb_Arena_Fuse(self.raw(), other.raw()) };
-        if !success {
-            // Fusing can fail if any of the arenas has an initial block i.e. the arena is
-            // backed by a preallocated chunk of memory that it doesn't own and thus cannot
-            // lifetime extend. This function panics because this is typically not a
-            // recoverable error but a logic bug in a program.
-            panic!("Could not fuse two UPB arenas.");
+    /// The dump holds no addresses, so building the same data twice gives the
+    /// same snapshot regardless of where upb placed its blocks. This suits
+    /// golden tests of built messages. With the arena-deterministic feature,
+    /// allocations are zeroed so bytes never written are reproducible too.
+    ///
+    /// # Safety
+    /// - No value with padding may have been copied into this arena, since
+    ///   padding bytes are uninitialized even in zeroed memory.
+    #[cfg(feature = "arena-deterministic")]
+    pub unsafe fn snapshot(&self) -> Vec<u8> {
+        let mut snapshot = Vec::new();
+        for &(ptr, size) in self.allocations.borrow().iter() {
+            snapshot.extend_from_slice(&(size as u64).to_le_bytes());
+            // SAFETY: `ptr` is an allocation of `size` bytes from this arena, which was
+            // zeroed and then only written with values without padding, as promised by
+            // the caller.
+            snapshot.extend_from_slice(unsafe { slice::from_raw_parts(ptr.as_ptr(), size) });
         }
+        snapshot
     }
-}
 
-impl Default for Arena {
-    fn default() -> Self {
-        Self::new()
+    /// Returns whether the canaries around every allocation made through
+    /// malloc() are intact, i.e. nothing has written past either end of an
+    /// allocation.
+    #[cfg(feature = "arena-canary")]
+    pub fn verify_canaries(&self) -> bool {
+        self.canaries.borrow().iter().all(|&(ptr, size)| {
+            // SAFETY: `ptr` is an allocation of `size` bytes from this arena with a
+            // canary on each side.
+            unsafe {
+                ptr.as_ptr().sub(CANARY.len()).cast::<[u8; 8]>().read() == CANARY
+                    && ptr.as_ptr().add(size).cast::<[u8; 8]>().read_unaligned() == CANARY
+            }
+        })
     }
-}
 
-impl Drop for Arena {
+    /// Bookkeeping run after every allocation from the arena, which handed
+    /// out `size` bytes (0 if it failed).
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
     #[inline]
-    fn drop(&mut self) {
-        unsafe {
-            upb_Arena_Free(self.raw);
+    fn after_alloc(&self, size: usize) {
+        #[cfg(feature = "arena-provenance")]
+        self.last_alloc_site.set(Some(Location::caller()));
+        #[cfg(feature = "arena-pressure")]
+        if let Some(pressure) = &self.pressure {
+            if size > 0 && !pressure.fired.get() {
+                let space_allocated = self.space_allocated();
+                if space_allocated > pressure.bytes {
+                    pressure.fired.set(true);
+                    (pressure.on_exceed)(space_allocated);
+                }
+            }
         }
+        #[cfg(not(feature = "arena-pressure"))]
+        let _ = size;
     }
-}
 
-extern "C" {
-    // `Option<NonNull<T: Sized>>` is ABI-compatible with `*mut T`
-    fn upb_Arena_New() -> Option<RawArena>;
-    fn upb_Arena_Free(arena: RawArena);
-    fn upb_Arena_Malloc(arena: RawArena, size: usize) -> *mut u8;
-    fn upb_Arena_Fuse(arena1: RawArena, arena2: RawArena) -> bool;
-}
+    /// Allocates `size` bytes aligned to `align`, which may be larger than
+    /// `UPB_MALLOC_ALIGN`. Returns None if the allocation failed.
+    ///
+    /// Over-aligned allocations are made by over-allocating by up to
+    /// `align - UPB_MALLOC_ALIGN` bytes and skipping the unaligned prefix, so
+    /// they waste more arena space than ordinary allocations.
+    ///
+    /// Panics if `align` is not a power of two.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    pub fn alloc_aligned(&self, size: usize, align: usize) -> Option<&mut [MaybeUninit<u8>]> {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let padding = align.saturating_sub(UPB_MALLOC_ALIGN);
+        let ptr = self.alloc_ptr(size.checked_add(padding)?, 1)?.as_ptr();
+        let offset = ptr.align_offset(align);
+        debug_assert!(offset <= padding);
+        #[cfg(feature = "arena-tracking")]
+        {
+            self.alignment_waste.set(self.alignment_waste.get() + padding);
+            // malloc() counted the padded size; the histogram wants the requested one.
+            let mut histogram = self.allocation_histogram.borrow_mut();
+            histogram[histogram_bucket(size + padding)] -= 1;
+            histogram[histogram_bucket(size)] += 1;
+        }
+        // The skipped prefix depends on the address, so only the aligned part
+        // goes in the snapshot.
+        #[cfg(feature = "arena-deterministic")]
+        if let Some(entry) = self.allocations.borrow_mut().last_mut() {
+            // SAFETY: `ptr.add(offset)` is within the allocation, so non-null.
+            *entry = (unsafe { NonNull::new_unchecked(ptr.add(offset)) }, size);
+        }
+        // SAFETY:
+        // - `ptr` is valid for `size + padding` bytes, and `offset <= padding` since
+        //   `ptr` is `UPB_MALLOC_ALIGN` aligned.
+        // - `[MaybeUninit<u8>]` has no validity requirements.
+        Some(unsafe { slice::from_raw_parts_mut(ptr.add(offset).cast(), size) })
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use googletest::gtest;
+    /// Returns the bytes over-allocated by `alloc_aligned` so far, and so by
+    /// the copy functions which use it for over-aligned types.
+    ///
+    /// This counts the whole padding of each over-aligned allocation, both the
+    /// skipped prefix and the unused remainder after the aligned part.
+    #[cfg(feature = "arena-tracking")]
+    pub fn alignment_waste(&self) -> usize {
+        self.alignment_waste.get()
+    }
 
-    #[gtest]
-    fn assert_arena_linked() {
-        use super::super::assert_linked;
-        assert_linked!(upb_Arena_New);
-        assert_linked!(upb_Arena_Free);
-        assert_linked!(upb_Arena_Malloc);
-        assert_linked!(upb_Arena_Fuse);
+    /// Returns the number of allocations made so far in each power-of-two
+    /// size bucket: bucket `i` counts sizes in `(2^(i-1), 2^i]`, with sizes 0
+    /// and 1 in bucket 0 and anything over 2^30 in bucket 31.
+    ///
+    /// The sizes are those requested, before any canary or `alloc_aligned`
+    /// padding, so this shows which size classes or initial block size would
+    /// suit a workload.
+    #[cfg(feature = "arena-tracking")]
+    pub fn allocation_histogram(&self) -> [usize; 32] {
+        *self.allocation_histogram.borrow()
     }
 
-    #[gtest]
-    fn raw_ffi_test() {
-        // SAFETY: FFI unit test uses C API under expected patterns.
+    /// Allocates `size` bytes rounded up to a multiple of the 4096 byte page
+    /// size, aligned to a page boundary. Returns None if the allocation
+    /// failed.
+    ///
+    /// This suits buffers handed off to DMA-capable devices. The returned slice
+    /// includes the padding up to the page boundary, and in addition up to a
+    /// page of arena memory is skipped to reach the aligned start.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_page_aligned(&self, size: usize) -> Option<&mut [MaybeUninit<u8>]> {
+        const PAGE_SIZE: usize = 4096;
+        self.alloc_aligned(size.checked_next_multiple_of(PAGE_SIZE)?, PAGE_SIZE)
+    }
+
+    /// Same as checked_alloc() but with the alignment known statically, so that
+    /// `ALIGN > UPB_MALLOC_ALIGN` is a build error rather than a panic.
+    ///
+    /// ```compile_fail
+    /// let arena = upb::Arena::new();
+    /// arena.alloc_const_align::<64>(8);
+    /// ```
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    pub fn alloc_const_align<const ALIGN: usize>(
+        &self,
+        size: usize,
+    ) -> Option<&mut [MaybeUninit<u8>]> {
+        #[allow(clippy::let_unit_value)]
+        let () = AssertAlignSupported::<ALIGN>::OK;
+        // SAFETY: ALIGN <= UPB_MALLOC_ALIGN asserted at compile time.
+        unsafe { self.alloc(size, ALIGN) }
+    }
+
+    /// Shrinks a previous allocation from this arena to `size` bytes.
+    ///
+    /// If it was the most recent allocation, the freed tail is returned to the
+    /// arena to be reused by the next allocation; otherwise this is a no-op.
+    /// Either way `ptr` stays valid for `size` bytes.
+    ///
+    /// # Safety
+    /// - `ptr` must have been returned by an allocation of `oldsize` bytes from
+    ///   this arena.
+    /// - `size` must be no larger than `oldsize`.
+    /// - The bytes past `size` must not be accessed after this call.
+    #[track_caller]
+    pub unsafe fn shrink_last(&self, ptr: NonNull<u8>, oldsize: usize, size: usize) {
+        debug_assert!(size <= oldsize);
+        // SAFETY: `size <= oldsize` and `ptr` is valid for `oldsize` bytes.
+        self.debug_assert_not_readonly(unsafe { ptr.as_ptr().add(size) }, oldsize - size);
+        #[cfg(not(feature = "arena-canary"))]
+        // SAFETY:
+        // - `self.raw` is a valid UPB arena and `ptr` is an allocation of `oldsize` bytes from it,
+        //   as promised by the caller.
+        // - `upb_Arena_Realloc` guarantees that shrinking is done in place.
+        let new_ptr = unsafe { upb_Arena_Realloc(self.raw, ptr.as_ptr(), oldsize, size) };
+        #[cfg(not(feature = "arena-canary"))]
+        debug_assert_eq!(new_ptr, ptr.as_ptr());
+        #[cfg(feature = "arena-canary")]
+        // SAFETY:
+        // - the allocation made by `malloc` starts a canary before `ptr` and ends a canary after
+        //   `oldsize`.
+        // - `upb_Arena_Realloc` guarantees that shrinking is done in place, so the new trailing
+        //   canary is within the shrunk allocation.
         unsafe {
-            let arena = upb_Arena_New().unwrap();
-            let bytes = upb_Arena_Malloc(arena, 3);
-            *bytes.add(2) = 7;
-            upb_Arena_Free(arena);
+            let base = ptr.as_ptr().sub(CANARY.len());
+            let padding = 2 * CANARY.len();
+            let new_base = upb_Arena_Realloc(self.raw, base, oldsize + padding, size + padding);
+            debug_assert_eq!(new_base, base);
+            ptr.as_ptr().add(size).cast::<[u8; 8]>().write_unaligned(CANARY);
+            if let Some(entry) = self.canaries.borrow_mut().iter_mut().rev().find(|e| e.0 == ptr) {
+                entry.1 = size;
+            }
+        }
+        #[cfg(feature = "arena-deterministic")]
+        if let Some(entry) = self.allocations.borrow_mut().iter_mut().rev().find(|e| e.0 == ptr) {
+            entry.1 = size;
         }
     }
 
-    #[gtest]
-    fn test_arena_new_and_free() {
+    /// Allocates room for `cap` elements, lets `decode` fill a prefix of them,
+    /// and returns the filled prefix.
+    ///
+    /// `decode` returns the number of elements it wrote; the unused tail of
+    /// the allocation is given back with `shrink_last` so it does not count
+    /// against later allocations. Returns None if the allocation failed.
+    ///
+    /// # Safety
+    /// - `decode` must initialize the first `n` elements of the slice it is
+    ///   passed, where `n` is the value it returns.
+    pub unsafe fn alloc_then_truncate<T: Copy>(
+        &self,
+        cap: usize,
+        decode: impl FnOnce(&mut [MaybeUninit<T>]) -> usize,
+    ) -> Option<&[T]> {
+        Self::assert_align_supported::<T>("alloc_then_truncate");
+        let size = size_of::<T>().checked_mul(cap)?;
+        let alloc = self.alloc_ptr(size, align_of::<T>())?;
+        // SAFETY: `alloc` is valid for `cap` elements of `T` and aligned for `T`.
+        let uninit =
+            unsafe { slice::from_raw_parts_mut(alloc.cast::<MaybeUninit<T>>().as_ptr(), cap) };
+        let len = decode(uninit);
+        assert!(len <= cap, "decoded {len} elements into a buffer of {cap}");
+        // SAFETY:
+        // - `alloc` is an allocation of `size` bytes from this arena, and the bytes
+        //   past `len` elements are not accessed again.
+        // - the caller promises the first `len` elements were initialized.
+        unsafe {
+            self.shrink_last(alloc, size, len * size_of::<T>());
+            Some(slice::from_raw_parts(alloc.cast::<T>().as_ptr(), len))
+        }
+    }
+
+    /// Copies the T into this arena and returns a pointer to the T data inside
+    /// the arena. Returns None if the allocation failed.
+    ///
+    /// Any padding bytes in the copy are uninitialized; use `uninit_bytes_of`
+    /// to view the copy as bytes.
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    pub fn copy_in<'a, T: Copy>(&'a self, data: &T) -> Option<&'a T> {
+        Self::assert_align_supported::<T>("copy_in");
+        // `Copy` already rules out drop glue; this keeps it a build error should the
+        // bound ever be relaxed.
+        #[allow(clippy::let_unit_value)]
+        let () = AssertNoDropGlue::<T>::OK;
+        let size = size_of_val(data);
+        let align = align_of_val(data);
+
+        self.alloc_ptr(size, align).map(|alloc| {
+            // SAFETY:
+            // - alloc is valid for `size` bytes and is the uninit bytes are written to not
+            //   read from until written.
+            // - T is copy so copying the bytes of the value is sound.
+            unsafe {
+                let alloc = alloc.cast::<T>().as_ptr();
+                alloc.write(*data);
+                shadow_record(slice::from_ref(&*alloc));
+                &*alloc
+            }
+        })
+    }
+
+    /// Same as copy_in() but always inlined, so the copy of a small `T` is
+    /// emitted inline rather than as a call to `memcpy`.
+    ///
+    /// `T` must be at most 64 bytes; larger types are a build error and should
+    /// use copy_in() instead.
+    ///
+    /// ```compile_fail
+    /// let arena = upb::Arena::new();
+    /// arena.copy_small_in(&[0u8; 65]);
+    /// ```
+    #[inline(always)]
+    pub fn copy_small_in<T: Copy>(&self, value: &T) -> Option<&T> {
+        #[allow(clippy::let_unit_value)]
+        let () = AssertSmall::<T>::OK;
+        Self::assert_align_supported::<T>("copy_small_in");
+        let alloc = self.alloc_ptr(size_of::<T>(), align_of::<T>())?.cast::<T>().as_ptr();
+        // SAFETY:
+        // - `alloc` is valid for writes of one `T` and aligned for it.
+        // - T is copy so copying the bytes of the value is sound.
+        unsafe {
+            ptr::write(alloc, *value);
+            shadow_record(slice::from_ref(&*alloc));
+            Some(&*alloc)
+        }
+    }
+
+    /// Copies the `Option<T>` into this arena. Returns None if the allocation
+    /// failed.
+    ///
+    /// The copy has the same layout as `value`, so types with a niche keep it:
+    /// an `Option<NonZeroU32>` or `Option<&T>` takes no more arena space than
+    /// the bare value.
+    pub fn copy_option_in<T: Copy>(&self, value: Option<T>) -> Option<&Option<T>> {
+        self.copy_in(&value)
+    }
+
+    /// Copies `value`, which may be `#[repr(packed)]`, into this arena.
+    /// Returns None if the allocation failed.
+    ///
+    /// Taking `value` by value means the caller never has to form a reference
+    /// into a packed struct, which is UB for misaligned fields; moving it out
+    /// copies its bytes without reading any field. The arena copy starts on a
+    /// `UPB_MALLOC_ALIGN` boundary even if the source was misaligned, though
+    /// its own packed fields are still laid out at their packed offsets.
+    pub fn copy_packed_in<T: Copy>(&self, value: T) -> Option<&T> {
+        self.copy_in(&value)
+    }
+
+    /// Copies the T into this arena and passes the copy to `f`, returning its
+    /// result. Returns None if the allocation failed.
+    ///
+    /// This keeps the borrow of the copy scoped to `f`, so it can be used
+    /// inline in an expression.
+    pub fn with_copy_in<T: Copy, R>(&self, data: &T, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.copy_in(data).map(f)
+    }
+
+    /// Validates that `raw` is a discriminant in `MIN..=MAX`, then copies it
+    /// into this arena as a `T`. Returns None if `raw` is out of range or
+    /// if the allocation failed.
+    ///
+    /// This guards against materializing an invalid enum value from untrusted
+    /// wire data. Panics if `T` is not the size of an `i32`.
+    ///
+    /// # Safety
+    /// - `T` must be a `#[repr(i32)]` enum (or otherwise have the layout of an
+    ///   `i32`) for which every value in `MIN..=MAX` is a valid discriminant.
+    pub unsafe fn copy_enum_in<T: Copy, const MIN: i32, const MAX: i32>(
+        &self,
+        raw: i32,
+    ) -> Option<&T> {
+        assert_eq!(size_of::<T>(), size_of::<i32>(), "enum must be repr(i32)");
+        if !(MIN..=MAX).contains(&raw) {
+            return None;
+        }
+        // SAFETY: `T` has the layout of an `i32` and `raw` is a valid discriminant of
+        // it, as promised by the caller.
+        let value = unsafe { ptr::read((&raw as *const i32).cast::<T>()) };
+        self.copy_in(&value)
+    }
+
+    /// Copies the bytes of the union `value` into this arena and returns a
+    /// view of the copy as its variant `V`. Returns None if the allocation
+    /// failed.
+    ///
+    /// Panics if `V` is larger than `U` or is not supported by the arena's
+    /// alignment.
+    ///
+    /// # Safety
+    /// - `V` must be the active variant of `value`: the first `size_of::<V>()`
+    ///   bytes of `value` must be initialized and form a valid `V`.
+    pub unsafe fn copy_union_as_in<U: Copy, V: Copy>(&self, value: &U) -> Option<&V> {
+        assert!(size_of::<V>() <= size_of::<U>(), "variant is larger than the union");
+        Self::assert_align_supported::<V>("copy_union_as_in");
+        let copy = self.copy_in(value)?;
+        // SAFETY:
+        // - the copy is aligned for `V`, since arena allocations are aligned to
+        //   UPB_MALLOC_ALIGN and `V` needs no more than that.
+        // - the first `size_of::<V>()` bytes are a valid `V`, as promised by the
+        //   caller.
+        Some(unsafe { &*(copy as *const U).cast::<V>() })
+    }
+
+    /// Moves the value into this arena and returns a mutable reference to it.
+    /// Returns None if the allocation failed.
+    ///
+    /// The arena never runs destructors, so `T`'s `Drop` (if any) will not run.
+    /// Panics if `T` is over-aligned, naming the calling function `op`.
+    #[allow(clippy::mut_from_ref)]
+    #[track_caller]
+    fn emplace<T>(&self, op: &str, value: T) -> Option<&mut T> {
+        Self::assert_align_supported::<T>(op);
+        self.alloc_ptr(size_of::<T>(), align_of::<T>()).map(|alloc| {
+            // SAFETY:
+            // - alloc is valid for `size_of::<T>()` bytes and is aligned for `T`.
+            // - the uninit bytes are written to before being read from.
+            unsafe {
+                let alloc = alloc.cast::<T>().as_ptr();
+                alloc.write(value);
+                &mut *alloc
+            }
+        })
+    }
+
+    /// Moves the value into this arena and returns it as an unsized `Dyn`
+    /// reference, for example `&mut dyn FnMut()`.
+    ///
+    /// `coerce` performs the unsizing coercion, since that can not be done
+    /// generically; it is normally just `|x| x`. Returns None if the allocation
+    /// failed.
+    ///
+    /// The arena never runs destructors, so `T`'s `Drop` (if any) will not run
+    /// and anything it owns will be leaked.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_dyn<'a, T: 'a, Dyn: ?Sized>(
+        &'a self,
+        value: T,
+        coerce: impl FnOnce(&'a mut T) -> &'a mut Dyn,
+    ) -> Option<&'a mut Dyn> {
+        self.emplace("alloc_dyn", value).map(coerce)
+    }
+
+    /// Moves the value into this arena and returns it pinned. Returns None if
+    /// the allocation failed.
+    ///
+    /// Arena memory never moves, and is not reused until the arena is dropped,
+    /// so the value stays at the same address for the returned lifetime. Since
+    /// the arena frees its memory without running destructors, `Pin`'s drop
+    /// guarantee only holds for types without drop glue; using a `T` that
+    /// needs drop is a build error.
+    ///
+    /// ```compile_fail
+    /// let arena = upb::Arena::new();
+    /// arena.alloc_pinned(String::new());
+    /// ```
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_pinned<T>(&self, value: T) -> Option<Pin<&mut T>> {
+        #[allow(clippy::let_unit_value)]
+        let () = AssertNoDropGlue::<T>::OK;
+        // SAFETY:
+        // - the value is never moved out of the arena allocation.
+        // - the allocation is not freed until the arena is, and dropping `T` is a no-op
+        //   so freeing it without running the destructor is allowed.
+        self.emplace("alloc_pinned", value).map(|value| unsafe { Pin::new_unchecked(value) })
+    }
+
+    /// Copies the str into this arena and returns a pointer to the T data
+    /// inside the arena. Returns None if the allocation failed.
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    pub fn copy_str_in<'a>(&'a self, s: &str) -> Option<&'a str> {
+        self.copy_slice_in(s.as_bytes()).map(|copied_bytes| {
+            // SAFETY: `copied_bytes` has same contents as `s` and so must meet &str
+            // criteria.
+            unsafe { core::str::from_utf8_unchecked(copied_bytes) }
+        })
+    }
+
+    /// Copies the str into this arena if it is no longer than `max_len` bytes.
+    ///
+    /// Oversized strings are rejected before allocating anything, which makes
+    /// this a cheap defense against untrusted input. Returns
+    /// `ArenaError::OutOfMemory` if the allocation failed.
+    pub fn copy_str_in_bounded<'a>(
+        &'a self,
+        s: &str,
+        max_len: usize,
+    ) -> Result<&'a str, ArenaError> {
+        if s.len() > max_len {
+            return Err(ArenaError::LengthExceeded(LengthExceeded { len: s.len(), max_len }));
+        }
+        self.copy_str_in(s).ok_or(ArenaError::OutOfMemory)
+    }
+
+    /// Copies `bytes` into this arena as a str, replacing each invalid UTF-8
+    /// sequence with U+FFFD like `String::from_utf8_lossy`. Returns None if the
+    /// allocation failed.
+    ///
+    /// The repaired string is written straight into a single allocation, with
+    /// no intermediate `String`.
+    pub fn copy_utf8_lossy_in<'a>(&'a self, bytes: &[u8]) -> Option<&'a str> {
+        const REPLACEMENT: &str = "\u{FFFD}";
+
+        // Calls `f` on each valid run and each replacement, in order.
+        fn for_each_part(mut bytes: &[u8], mut f: impl FnMut(&str)) {
+            loop {
+                match core::str::from_utf8(bytes) {
+                    Ok(valid) => return f(valid),
+                    Err(e) => {
+                        let (valid, rest) = bytes.split_at(e.valid_up_to());
+                        // SAFETY: `from_utf8` checked that the bytes up to
+                        // `valid_up_to` are valid UTF-8.
+                        f(unsafe { core::str::from_utf8_unchecked(valid) });
+                        f(REPLACEMENT);
+                        match e.error_len() {
+                            Some(len) => bytes = &rest[len..],
+                            None => return,
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut len = 0;
+        for_each_part(bytes, |part| len += part.len());
+        let alloc = self.alloc_ptr(len, 1)?.as_ptr();
+        let mut written = 0;
+        for_each_part(bytes, |part| {
+            // SAFETY: `alloc` is valid for `len` bytes, which is the total length of
+            // the parts, so for `part.len()` bytes after the `written` so far.
+            unsafe { ptr::copy_nonoverlapping(part.as_ptr(), alloc.add(written), part.len()) };
+            written += part.len();
+        });
+        // SAFETY: all `len` bytes were written.
+        let copy = unsafe { slice::from_raw_parts(alloc, len) };
+        shadow_record(copy);
+        // SAFETY: a sequence of strs is valid UTF-8.
+        Some(unsafe { core::str::from_utf8_unchecked(copy) })
+    }
+
+    /// Copies the strings into this arena as NUL-terminated C strings and
+    /// returns an argv-style array of pointers to them, followed by a null
+    /// pointer.
+    ///
+    /// Strings containing a NUL byte are rejected before allocating anything.
+    /// Returns `Ok(None)` if an allocation failed.
+    pub fn copy_argv_in<'a>(
+        &'a self,
+        args: &[&str],
+    ) -> Result<Option<&'a [*const c_char]>, InteriorNul> {
+        for (index, arg) in args.iter().enumerate() {
+            if let Some(position) = arg.bytes().position(|b| b == 0) {
+                return Err(InteriorNul { index, position });
+            }
+        }
+        let mut argv = Vec::with_capacity(args.len() + 1);
+        for arg in args {
+            let Some(ptr) = self.alloc_ptr(arg.len() + 1, 1) else { return Ok(None) };
+            let ptr = ptr.as_ptr();
+            // SAFETY: `ptr` is valid for `arg.len() + 1` bytes.
+            unsafe {
+                ptr::copy_nonoverlapping(arg.as_ptr(), ptr, arg.len());
+                ptr.add(arg.len()).write(0);
+                shadow_record(slice::from_raw_parts(ptr, arg.len() + 1));
+            }
+            argv.push(ptr.cast_const().cast::<c_char>());
+        }
+        argv.push(ptr::null());
+        Ok(self.copy_slice_in(&argv))
+    }
+
+    /// Copies the slice into this arena and returns a pointer to the T data
+    /// inside the arena. Returns None if the allocation failed.
+    ///
+    /// This is a single `memcpy` into a single allocation regardless of size;
+    /// upb serves allocations larger than its current block from a dedicated
+    /// block, so large slices do not need a separate code path.
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    pub fn copy_slice_in<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a [T]> {
+        Self::assert_align_supported::<T>("copy_slice_in");
+        let size = size_of_val(data);
+        let align = align_of_val(data);
+        self.alloc_ptr(size, align).map(|alloc| {
+            let alloc = alloc.cast::<T>().as_ptr();
+            // SAFETY:
+            // - uninit_alloc is valid for `layout.len()` bytes and is the uninit bytes are
+            //   written to not read from until written.
+            // - T is copy so copying the bytes of the values is sound.
+            let copy = unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr(), alloc, data.len());
+                slice::from_raw_parts(alloc, data.len())
+            };
+            shadow_record(copy);
+            copy
+        })
+    }
+
+    /// Same as copy_slice_in(), but also marks the copy as logically const.
+    ///
+    /// In debug builds, `shrink_last` then panics if the tail it releases
+    /// overlaps the copy, since that would let a later allocation hand out
+    /// mutable memory aliasing it. That is the only check made: fresh
+    /// allocations never overlap live memory, so `copy_slice_in_mut` and the
+    /// other allocation functions are not checked, and writes through pointers
+    /// cast from the copy are not caught. Release builds do no tracking.
+    #[cfg_attr(feature = "arena-provenance", track_caller)]
+    pub fn copy_slice_in_readonly<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a [T]> {
+        let copy = self.copy_slice_in(data)?;
+        #[cfg(debug_assertions)]
+        if !copy.is_empty() {
+            let region = copy.as_ptr() as usize;
+            self.readonly.borrow_mut().insert(region, region + size_of_val(copy));
+        }
+        Some(copy)
+    }
+
+    /// Returns an iterator which copies each element of `data` into this
+    /// arena as it is reached, so elements never iterated over are never
+    /// copied.
+    ///
+    /// The copies made persist for the arena's lifetime, even once the
+    /// iterator is dropped. Iteration stops early if an allocation fails.
+    pub fn lazy_copy_in<'a, T: Copy>(&'a self, data: &'a [T]) -> impl Iterator<Item = &'a T> {
+        data.iter().map_while(move |value| self.copy_in(value))
+    }
+
+    /// Same as copy_slice_in() but never panics, returning an error for
+    /// over-aligned `T` or if the allocation failed.
+    pub fn try_copy_slice_in<'a, T: Copy>(&'a self, data: &[T]) -> Result<&'a [T], ArenaError> {
+        if align_of::<T>() > UPB_MALLOC_ALIGN {
+            return Err(ArenaError::AlignmentTooLarge);
+        }
+        self.copy_slice_in(data).ok_or(ArenaError::OutOfMemory)
+    }
+
+    /// Same as copy_slice_in() but also accepts over-aligned `T`, which it
+    /// copies into an `alloc_aligned` allocation instead of panicking.
+    /// Returns None if the allocation failed.
+    ///
+    /// `align_of::<T>()` is a constant, so the choice between the two paths is
+    /// made at compile time.
+    pub fn copy_slice_in_auto<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a [T]> {
+        if align_of::<T>() <= UPB_MALLOC_ALIGN {
+            return self.copy_slice_in(data);
+        }
+        let alloc = self.alloc_aligned(size_of_val(data), align_of::<T>())?;
+        let alloc = alloc.as_mut_ptr().cast::<T>();
+        // SAFETY: `alloc` is valid for `data.len()` elements of `T`, is aligned for
+        // `T`, and is written before being read.
+        let copy = unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), alloc, data.len());
+            slice::from_raw_parts(alloc, data.len())
+        };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Copies the bytes into this arena and returns the copy along with the
+    /// CRC-32 (IEEE) of the bytes. Returns None if the allocation failed.
+    ///
+    /// The checksum is computed in the same pass over `data` as the copy.
+    pub fn copy_slice_in_checked<'a>(&'a self, data: &[u8]) -> Option<(&'a [u8], u32)> {
+        Self::assert_align_supported::<u8>("copy_slice_in_checked");
+        let alloc = self.alloc_ptr(data.len(), 1)?.as_ptr();
+        let mut crc = !0u32;
+        for (i, &byte) in data.iter().enumerate() {
+            crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize];
+            // SAFETY: `alloc` is valid for `data.len()` bytes.
+            unsafe { alloc.add(i).write(byte) };
+        }
+        // SAFETY: all `data.len()` bytes of `alloc` were written above.
+        let copy = unsafe { slice::from_raw_parts(alloc, data.len()) };
+        shadow_record(copy);
+        Some((copy, !crc))
+    }
+
+    /// Copies the slice into this arena and returns the copy along with the
+    /// minimum and maximum values, or None for the range if `data` is empty.
+    /// Returns None if the allocation failed.
+    ///
+    /// The range is computed in the same pass over `data` as the copy. Values
+    /// that are unordered relative to the current range (such as NaN) are not
+    /// taken as the new minimum or maximum.
+    #[allow(clippy::type_complexity)]
+    pub fn copy_slice_in_minmax<'a, T: Copy + PartialOrd>(
+        &'a self,
+        data: &[T],
+    ) -> Option<(&'a [T], Option<(T, T)>)> {
+        Self::assert_align_supported::<T>("copy_slice_in_minmax");
+        let alloc = self.alloc_ptr(size_of_val(data), align_of_val(data))?;
+        let alloc = alloc.cast::<T>().as_ptr();
+        let mut range: Option<(T, T)> = None;
+        for (i, &x) in data.iter().enumerate() {
+            range = Some(match range {
+                None => (x, x),
+                Some((min, max)) => (if x < min { x } else { min }, if x > max { x } else { max }),
+            });
+            // SAFETY: `alloc` is valid for `data.len()` elements of `T`.
+            unsafe { alloc.add(i).write(x) };
+        }
+        // SAFETY: all `data.len()` elements of `alloc` were written above.
+        let copy = unsafe { slice::from_raw_parts(alloc, data.len()) };
+        shadow_record(copy);
+        Some((copy, range))
+    }
+
+    /// Maps `data` through `f` into `scratch` and copies the results into this
+    /// arena. Returns None if the allocation failed.
+    ///
+    /// `scratch` is cleared first and keeps its capacity, so reusing it across
+    /// calls avoids a heap allocation per call.
+    pub fn copy_transform_in<'a, T: Copy, U: Copy>(
+        &'a self,
+        data: &[T],
+        scratch: &mut Vec<U>,
+        f: impl FnMut(&T) -> U,
+    ) -> Option<&'a [U]> {
+        scratch.clear();
+        scratch.extend(data.iter().map(f));
+        self.copy_slice_in(scratch)
+    }
+
+    /// Copies the slice into this arena and sorts the copy, leaving `data`
+    /// untouched. Returns None if the allocation failed.
+    pub fn copy_sorted_in<'a, T: Copy + Ord>(&'a self, data: &[T]) -> Option<&'a [T]> {
+        self.copy_sorted_by_in(data, T::cmp)
+    }
+
+    /// Same as copy_sorted_in() but sorts with the `compare` function.
+    ///
+    /// The sort is unstable, so it does not allocate beyond the copy itself.
+    pub fn copy_sorted_by_in<'a, T: Copy>(
+        &'a self,
+        data: &[T],
+        compare: impl FnMut(&T, &T) -> Ordering,
+    ) -> Option<&'a [T]> {
+        let copy = self.copy_slice_in_mut(data)?;
+        copy.sort_unstable_by(compare);
+        Some(copy)
+    }
+
+    /// Copies the slice into this arena sorted by `key`, along with the
+    /// permutation mapping each sorted position to the index in `data` it came
+    /// from. Returns None if an allocation failed or `data` has more than
+    /// `u32::MAX` elements.
+    ///
+    /// The sort is stable, so elements with equal keys keep their order; it
+    /// may use a temporary heap buffer, like `slice::sort_by_key`.
+    pub fn copy_stable_sorted_in<'a, T: Copy, K: Ord>(
+        &'a self,
+        data: &[T],
+        key: impl Fn(&T) -> K,
+    ) -> Option<(&'a [T], &'a [u32])> {
+        u32::try_from(data.len()).ok()?;
+        let permutation = self.alloc_default_slice::<u32>(data.len())?;
+        for (i, index) in permutation.iter_mut().enumerate() {
+            *index = i as u32;
+        }
+        permutation.sort_by_key(|&i| key(&data[i as usize]));
+        let sorted = self.copy_slice_in_mut(data)?;
+        for (value, &i) in sorted.iter_mut().zip(permutation.iter()) {
+            *value = data[i as usize];
+        }
+        Some((sorted, permutation))
+    }
+
+    /// Same as copy_slice_in() but returns a mutable reference to the copy.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) fn copy_slice_in_mut<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a mut [T]> {
+        Self::assert_align_supported::<T>("copy_slice_in_mut");
+        let alloc = self.alloc_ptr(size_of_val(data), align_of_val(data))?;
+        let alloc = alloc.cast::<T>().as_ptr();
+        // SAFETY:
+        // - `alloc` is valid for `data.len()` elements of `T` and is written before
+        //   being read.
+        // - T is copy so copying the bytes of the values is sound.
+        let copy = unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), alloc, data.len());
+            slice::from_raw_parts_mut(alloc, data.len())
+        };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Allocates `len` copies of `T::default()` in this arena and returns
+    /// them as a mutable slice to be patched by the caller. Returns None if
+    /// the allocation failed.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_default_slice<T: Copy + Default>(&self, len: usize) -> Option<&mut [T]> {
+        Self::assert_align_supported::<T>("alloc_default_slice");
+        let alloc = self.alloc_ptr(size_of::<T>().checked_mul(len)?, align_of::<T>())?;
+        let alloc = alloc.cast::<T>().as_ptr();
+        let value = T::default();
+        // SAFETY: `alloc` is valid for `len` elements of `T` and aligned for `T`, and
+        // all of them are written before the slice is created.
+        let copy = unsafe {
+            for i in 0..len {
+                alloc.add(i).write(value);
+            }
+            slice::from_raw_parts_mut(alloc, len)
+        };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Copies the pairs into this arena as two parallel slices of keys and
+    /// values, so that `keys[i]` and `values[i]` come from `pairs[i]`. Returns
+    /// None if either allocation failed.
+    pub fn copy_soa_in<'a, K: Copy, V: Copy>(
+        &'a self,
+        pairs: &[(K, V)],
+    ) -> Option<(&'a [K], &'a [V])> {
+        Self::assert_align_supported::<K>("copy_soa_in");
+        Self::assert_align_supported::<V>("copy_soa_in");
+        let keys = self.alloc_ptr(size_of::<K>() * pairs.len(), align_of::<K>())?;
+        let values = self.alloc_ptr(size_of::<V>() * pairs.len(), align_of::<V>())?;
+        let (keys, values) = (keys.cast::<K>().as_ptr(), values.cast::<V>().as_ptr());
+        for (i, &(k, v)) in pairs.iter().enumerate() {
+            // SAFETY: `keys` and `values` are valid for `pairs.len()` elements.
+            unsafe {
+                keys.add(i).write(k);
+                values.add(i).write(v);
+            }
+        }
+        // SAFETY: all `pairs.len()` elements of both allocations were written above.
+        let (keys, values) = unsafe {
+            (slice::from_raw_parts(keys, pairs.len()), slice::from_raw_parts(values, pairs.len()))
+        };
+        shadow_record(keys);
+        shadow_record(values);
+        Some((keys, values))
+    }
+
+    /// Same as copy_slice_in() but also returns an iterator over the copy in
+    /// reverse order, so both directions share the one arena copy.
+    pub fn copy_slice_in_rev<'a, T: Copy>(
+        &'a self,
+        data: &[T],
+    ) -> Option<(&'a [T], Rev<slice::Iter<'a, T>>)> {
+        let copy = self.copy_slice_in(data)?;
+        Some((copy, copy.iter().rev()))
+    }
+
+    /// Copies the bytes from the cursor's position to the end of its buffer
+    /// into this arena, and advances the cursor to the end. Returns None if
+    /// the allocation failed, in which case the cursor is not moved.
+    pub fn copy_cursor_remainder_in<'a>(
+        &'a self,
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Option<&'a [u8]> {
+        let buf = *cursor.get_ref();
+        let start = usize::try_from(cursor.position()).map_or(buf.len(), |p| p.min(buf.len()));
+        let copy = self.copy_slice_in(&buf[start..])?;
+        cursor.set_position(cursor.position().max(buf.len() as u64));
+        Some(copy)
+    }
+
+    /// Same as copy_slice_in() but copies with `ptr::copy` rather than
+    /// `ptr::copy_nonoverlapping`.
+    ///
+    /// The destination is always a fresh allocation, so it can not overlap
+    /// `data` even when `data` is itself owned by this arena; this variant
+    /// exists for callers that want the overlap-safe copy regardless.
+    pub fn copy_overlapping_in<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a [T]> {
+        Self::assert_align_supported::<T>("copy_overlapping_in");
+        let size = size_of_val(data);
+        let align = align_of_val(data);
+        self.alloc_ptr(size, align).map(|alloc| {
+            let alloc = alloc.cast::<T>().as_ptr();
+            // SAFETY:
+            // - `alloc` is valid for `data.len()` elements of `T`, and the uninit bytes are
+            //   written to before being read from.
+            // - T is copy so copying the bytes of the values is sound.
+            let copy = unsafe {
+                ptr::copy(data.as_ptr(), alloc, data.len());
+                slice::from_raw_parts(alloc, data.len())
+            };
+            shadow_record(copy);
+            copy
+        })
+    }
+
+    /// Same as copy_slice_in() but copies `chunk` elements at a time, calling
+    /// `on_chunk` between steps so long copies can be cooperatively cancelled.
+    ///
+    /// If `on_chunk` returns false the copy is abandoned and None is returned;
+    /// the partial allocation is given back to the arena if it is still the
+    /// most recent one, and otherwise freed with the arena. Also returns None
+    /// if the allocation failed. Panics if `chunk` is zero.
+    pub fn copy_slice_in_chunked<'a, T: Copy>(
+        &'a self,
+        data: &[T],
+        chunk: usize,
+        mut on_chunk: impl FnMut() -> bool,
+    ) -> Option<&'a [T]> {
+        assert!(chunk > 0, "chunk size must be non-zero");
+        Self::assert_align_supported::<T>("copy_slice_in_chunked");
+        let size = size_of_val(data);
+        let alloc = self.alloc_ptr(size, align_of_val(data))?;
+        let dst = alloc.cast::<T>().as_ptr();
+        for (i, src) in data.chunks(chunk).enumerate() {
+            if i > 0 && !on_chunk() {
+                // SAFETY: `alloc` is an allocation of `size` bytes from this arena and
+                // nothing refers to it.
+                unsafe { self.shrink_last(alloc, size, 0) };
+                return None;
+            }
+            // SAFETY: `dst` is valid for `data.len()` elements, and chunk `i` starts
+            // at element `i * chunk`.
+            unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst.add(i * chunk), src.len()) };
+        }
+        // SAFETY: every element of `dst` was initialized by the loop above.
+        let copy = unsafe { slice::from_raw_parts(dst, data.len()) };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Same as copy_slice_in() but copies `chunk` elements at a time, calling
+    /// `progress(done, total)` with the number of elements copied so far after
+    /// each one.
+    ///
+    /// Unlike copy_slice_in_chunked(), the copy can not be abandoned. Returns
+    /// None if the allocation failed. Panics if `chunk` is zero.
+    pub fn copy_slice_in_progress<'a, T: Copy>(
+        &'a self,
+        data: &[T],
+        chunk: usize,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Option<&'a [T]> {
+        assert!(chunk > 0, "chunk size must be non-zero");
+        Self::assert_align_supported::<T>("copy_slice_in_progress");
+        let alloc = self.alloc_ptr(size_of_val(data), align_of_val(data))?;
+        let dst = alloc.cast::<T>().as_ptr();
+        let mut done = 0;
+        for src in data.chunks(chunk) {
+            // SAFETY: `dst` is valid for `data.len()` elements, and the first `done`
+            // have been written.
+            unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst.add(done), src.len()) };
+            done += src.len();
+            progress(done, data.len());
+        }
+        // SAFETY: every element of `dst` was initialized by the loop above.
+        let copy = unsafe { slice::from_raw_parts(dst, data.len()) };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Copies the already-initialized slice into this arena and returns a
+    /// pointer to the T data inside the arena. Returns None if the allocation
+    /// failed.
+    ///
+    /// # Safety
+    /// - Every element of `data` must be initialized.
+    pub unsafe fn copy_init_slice_in<'a, T: Copy>(
+        &'a self,
+        data: &[MaybeUninit<T>],
+    ) -> Option<&'a [T]> {
+        self.copy_slice_in(data).map(|copy| {
+            // SAFETY:
+            // - `MaybeUninit<T>` has the same layout as `T`.
+            // - the caller promises every element is initialized, and `copy` has the same
+            //   contents as `data`.
+            unsafe { slice::from_raw_parts(copy.as_ptr().cast::<T>(), copy.len()) }
+        })
+    }
+
+    /// Copies the slice into this arena, checking each element against `pred`
+    /// as it is copied.
+    ///
+    /// Returns `Some(Err(index))` with the index of the first element for which
+    /// `pred` returns false; the partially written allocation is reclaimed
+    /// along with the rest of the arena. Returns None if the allocation failed.
+    pub fn copy_slice_in_validated<'a, T: Copy>(
+        &'a self,
+        data: &[T],
+        pred: impl Fn(&T) -> bool,
+    ) -> Option<Result<&'a [T], usize>> {
+        Self::assert_align_supported::<T>("copy_slice_in_validated");
+        let size = size_of_val(data);
+        let align = align_of_val(data);
+        let alloc = self.alloc_ptr(size, align)?.cast::<T>().as_ptr();
+        for (i, value) in data.iter().enumerate() {
+            if !pred(value) {
+                return Some(Err(i));
+            }
+            // SAFETY: `alloc` is valid for `data.len()` writes of `T`.
+            unsafe { alloc.add(i).write(*value) };
+        }
+        // SAFETY: all `data.len()` elements of `alloc` were initialized above.
+        let copy = unsafe { slice::from_raw_parts(alloc, data.len()) };
+        shadow_record(copy);
+        Some(Ok(copy))
+    }
+
+    /// Allocates `header_len + body.len()` contiguous bytes and copies `body`
+    /// into the tail, for framed data that should be written out in one go.
+    ///
+    /// Returns the uninitialized header for the caller to fill in, and the
+    /// copied body which immediately follows it in memory. Returns None if the
+    /// allocation failed.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_framed<'a>(
+        &'a self,
+        header_len: usize,
+        body: &[u8],
+    ) -> Option<(&'a mut [MaybeUninit<u8>], &'a [u8])> {
+        let size = header_len.checked_add(body.len())?;
+        let alloc = self.alloc_ptr(size, 1)?.as_ptr();
+        // SAFETY:
+        // - `alloc` is valid for `size` bytes, so the header and body ranges are in
+        //   bounds and do not overlap each other or `body`.
+        // - the body bytes are initialized before being viewed as `[u8]`.
+        let (header, body) = unsafe {
+            let body_ptr = alloc.add(header_len);
+            ptr::copy_nonoverlapping(body.as_ptr(), body_ptr, body.len());
+            (
+                slice::from_raw_parts_mut(alloc.cast(), header_len),
+                slice::from_raw_parts(body_ptr, body.len()),
+            )
+        };
+        shadow_record(body);
+        Some((header, body))
+    }
+
+    /// Copies the u32s into this arena as little-endian bytes, regardless of
+    /// the host byte order. Returns None if the allocation failed.
+    pub fn copy_u32_slice_le_in<'a>(&'a self, data: &[u32]) -> Option<&'a [u8]> {
+        self.copy_encoded_slice_in(data, u32::to_le_bytes)
+    }
+
+    /// Copies the u32s into this arena as big-endian bytes, regardless of the
+    /// host byte order. Returns None if the allocation failed.
+    pub fn copy_u32_slice_be_in<'a>(&'a self, data: &[u32]) -> Option<&'a [u8]> {
+        self.copy_encoded_slice_in(data, u32::to_be_bytes)
+    }
+
+    /// Copies the u64s into this arena as little-endian bytes, regardless of
+    /// the host byte order. Returns None if the allocation failed.
+    pub fn copy_u64_slice_le_in<'a>(&'a self, data: &[u64]) -> Option<&'a [u8]> {
+        self.copy_encoded_slice_in(data, u64::to_le_bytes)
+    }
+
+    /// Copies the u64s into this arena as big-endian bytes, regardless of the
+    /// host byte order. Returns None if the allocation failed.
+    pub fn copy_u64_slice_be_in<'a>(&'a self, data: &[u64]) -> Option<&'a [u8]> {
+        self.copy_encoded_slice_in(data, u64::to_be_bytes)
+    }
+
+    /// Copies the u32s into this arena both as native-endian bytes and as
+    /// byte-swapped bytes, in a single pass, and returns `(native, swapped)`.
+    /// Returns None if the allocation failed.
+    ///
+    /// On a little-endian host that is the little-endian encoding followed by
+    /// the big-endian one, and vice versa.
+    pub fn copy_u32_both_endian_in<'a>(&'a self, data: &[u32]) -> Option<(&'a [u8], &'a [u8])> {
+        let size = size_of_val(data);
+        let alloc = self.alloc_ptr(size.checked_mul(2)?, 1)?.as_ptr();
+        for (i, &value) in data.iter().enumerate() {
+            let (native, swapped) = (value.to_ne_bytes(), value.swap_bytes().to_ne_bytes());
+            // SAFETY: `alloc` is valid for `2 * size` bytes, and does not overlap the
+            // local arrays.
+            unsafe {
+                ptr::copy_nonoverlapping(native.as_ptr(), alloc.add(i * 4), 4);
+                ptr::copy_nonoverlapping(swapped.as_ptr(), alloc.add(size + i * 4), 4);
+            }
+        }
+        // SAFETY: both halves of `alloc` were initialized above.
+        let copy = unsafe { slice::from_raw_parts(alloc, 2 * size) };
+        shadow_record(copy);
+        Some(copy.split_at(size))
+    }
+
+    /// Writes `encode(x)` for each element of `data` contiguously into a new
+    /// allocation on this arena.
+    fn copy_encoded_slice_in<'a, T: Copy, const N: usize>(
+        &'a self,
+        data: &[T],
+        encode: impl Fn(T) -> [u8; N],
+    ) -> Option<&'a [u8]> {
+        let size = data.len().checked_mul(N)?;
+        let alloc = self.alloc_ptr(size, 1)?.as_ptr();
+        for (i, &value) in data.iter().enumerate() {
+            let bytes = encode(value);
+            // SAFETY: `alloc` is valid for `size` bytes and does not overlap `bytes`.
+            unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), alloc.add(i * N), N) };
+        }
+        // SAFETY: all `size` bytes of `alloc` were initialized above.
+        let copy = unsafe { slice::from_raw_parts(alloc, size) };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Fuse two arenas so they share the same lifetime.
+    ///
+    /// `fuse` will make it so that the memory allocated by `self` or `other` is
+    /// guaranteed to last until both `self` and `other` have been dropped.
+    /// The pointers returned by `Arena::alloc` will continue to be valid so
+    /// long as either `self` or `other` has not been dropped.
+    pub fn fuse(&self, other: &Arena) {
+        if !self.try_fuse(other) {
+            // Fusing can fail if any of the arenas has an initial block i.e. the arena is
+            // backed by a preallocated chunk of memory that it doesn't own and thus cannot
+            // lifetime extend. This function panics because this is typically not a
+            // recoverable error but a logic bug in a program.
+            panic!("Could not fuse two UPB arenas.");
+        }
+    }
+
+    /// Same as fuse() but returns false instead of panicking if the arenas
+    /// could not be fused, which is the case if either has an initial block.
+    pub fn try_fuse(&self, other: &Arena) -> bool {
+        // SAFETY: `self.raw()` and `other.raw()` are both valid UPB arenas.
+        unsafe { upb_Arena_Fuse(self.raw(), other.raw()) }
+    }
+
+    /// Fuses `other` into this arena and returns `data_in_other`, which then
+    /// lives as long as `self`. If the arenas can not be fused, copies
+    /// `data_in_other` into this arena instead and returns the copy. Returns
+    /// None if that allocation failed.
+    ///
+    /// # Safety
+    /// - `data_in_other` must have been allocated from `other` or an arena
+    ///   fused to it.
+    pub unsafe fn fuse_or_copy<T: Copy>(&self, other: &Arena, data_in_other: &[T]) -> Option<&[T]> {
+        if self.try_fuse(other) {
+            // SAFETY: `data_in_other` is memory of `other`, which now lives at least
+            // as long as `self`.
+            Some(unsafe { &*(data_in_other as *const [T]) })
+        } else {
+            self.copy_slice_in(data_in_other)
+        }
+    }
+
+    /// Registers `hook` to run when this `Arena` handle is dropped. Hooks run
+    /// in the reverse of the order they were registered, just before the
+    /// handle releases its reference to the underlying upb arena.
+    ///
+    /// This is tied to the handle, not to the arena's memory: if the arena is
+    /// fused, its memory stays alive until every arena in the group has been
+    /// dropped, so it may still be live when the hooks run and after. Hooks
+    /// are not run for arenas whose destructor is skipped, see `from_raw`.
+    pub fn on_handle_drop(&self, hook: Box<dyn FnOnce() + Send>) {
+        self.drop_hooks.get_or_init(Box::default).0.borrow_mut().push(hook);
+    }
+
+    /// Allocates a fresh arena which is fused with `self`, so that memory
+    /// allocated by either lives until both have been dropped.
+    ///
+    /// This is the equivalent of cloning a shared-ownership arena handle.
+    /// `Arena` deliberately does not implement `Clone`, since a clone that
+    /// quietly extends the lifetime of all of the original's memory is easy to
+    /// misuse.
+    pub fn new_fused(&self) -> Arena {
+        let arena = Arena::new();
+        arena.fuse(self);
+        arena
+    }
+
+    /// Copies `prefix` followed by `suffix` into `arena` as one slice. Returns
+    /// None if the allocation failed.
+    ///
+    /// Like every copy function, the result borrows from the arena passed in,
+    /// so library code can build data in its caller's arena by taking
+    /// `&'a Arena` and returning `&'a` references:
+    ///
+    /// ```
+    /// use upb::Arena;
+    ///
+    /// fn build_into<'a>(arena: &'a Arena, name: &str) -> Option<&'a [u8]> {
+    ///     Arena::extend_in(arena, b"name=", name.as_bytes())
+    /// }
+    ///
+    /// let arena = Arena::new();
+    /// let built = {
+    ///     let name = String::from("upb");
+    ///     build_into(&arena, &name).unwrap()
+    /// };
+    /// assert_eq!(built, b"name=upb");
+    /// ```
+    pub fn extend_in<'a, T: Copy>(arena: &'a Arena, prefix: &[T], suffix: &[T]) -> Option<&'a [T]> {
+        Self::assert_align_supported::<T>("extend_in");
+        let len = prefix.len().checked_add(suffix.len())?;
+        let size = size_of::<T>().checked_mul(len)?;
+        let alloc = arena.alloc_ptr(size, align_of::<T>())?.cast::<T>().as_ptr();
+        // SAFETY: `alloc` is valid for `len` elements of `T` and aligned for `T`,
+        // and both parts are written before the slice is read.
+        let copy = unsafe {
+            ptr::copy_nonoverlapping(prefix.as_ptr(), alloc, prefix.len());
+            ptr::copy_nonoverlapping(suffix.as_ptr(), alloc.add(prefix.len()), suffix.len());
+            slice::from_raw_parts(alloc, len)
+        };
+        shadow_record(copy);
+        Some(copy)
+    }
+
+    /// Fuses `a` and `b` and copies `data` into `b`, so the copy is valid for
+    /// as long as `b` lives even if `a` is dropped first. Returns None if the
+    /// allocation failed.
+    ///
+    /// Memory allocated by `a` outlives `a` once it is fused with `b`, but a
+    /// reference borrowed from `a` is still bound to `a`'s lifetime. Copying
+    /// into `b` instead gives the reference `b`'s lifetime, for data that
+    /// belongs with `a` but must survive it.
+    ///
+    /// Panics if the arenas could not be fused, see `fuse`.
+    pub fn copy_slice_in_surviving<'b, T: Copy>(
+        a: &Arena,
+        b: &'b Arena,
+        data: &[T],
+    ) -> Option<&'b [T]> {
+        a.fuse(b);
+        b.copy_slice_in(data)
+    }
+}
+
+macro_rules! impl_alloc_atomic {
+    ($($name:ident: $atomic:ident($int:ty)),* $(,)?) => {
+        impl Arena {
+            $(
+                #[doc = concat!("Allocates an `", stringify!($atomic), "` holding `initial` in this")]
+                /// arena and returns a shared reference to it. Returns None if the
+                /// allocation failed.
+                ///
+                /// Atomics have no drop glue, so never running their destructor is
+                /// fine, and the reference can be shared across threads even though
+                /// the arena itself can not.
+                pub fn $name(&self, initial: $int) -> Option<&$atomic> {
+                    self.emplace(stringify!($name), $atomic::new(initial)).map(|atomic| &*atomic)
+                }
+            )*
+        }
+    };
+}
+
+impl_alloc_atomic!(
+    alloc_atomic_u8: AtomicU8(u8),
+    alloc_atomic_u16: AtomicU16(u16),
+    alloc_atomic_u32: AtomicU32(u32),
+    alloc_atomic_u64: AtomicU64(u64),
+    alloc_atomic_usize: AtomicUsize(usize),
+);
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Arena {
+    #[inline]
+    fn drop(&mut self) {
+        // An arena which was given an id did so on this thread or before being sent
+        // here, so its increment of the count is visible.
+        if HANDLE_ID_COUNT.load(core::sync::atomic::Ordering::Relaxed) != 0 {
+            self.forget_handle_id();
+        }
+        if let Some(hooks) = self.drop_hooks.take() {
+            for hook in hooks.0.into_inner().into_iter().rev() {
+                hook();
+            }
+        }
+        unsafe {
+            upb_Arena_Free(self.raw);
+        }
+    }
+}
+
+extern "C" {
+    // `Option<NonNull<T: Sized>>` is ABI-compatible with `*mut T`
+    fn upb_Arena_New() -> Option<RawArena>;
+    fn upb_Arena_Free(arena: RawArena);
+    fn upb_Arena_Malloc(arena: RawArena, size: usize) -> *mut u8;
+    fn upb_Arena_Realloc(arena: RawArena, ptr: *mut u8, oldsize: usize, size: usize) -> *mut u8;
+    fn upb_Arena_Fuse(arena1: RawArena, arena2: RawArena) -> bool;
+    fn upb_Arena_SpaceAllocated(arena: RawArena, fused_count: *mut usize) -> usize;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    extern "C" {
+        fn upb_Arena_Init(mem: *mut u8, n: usize, alloc: *mut upb_alloc) -> Option<RawArena>;
+    }
+
+    #[gtest]
+    fn assert_arena_linked() {
+        use super::super::assert_linked;
+        assert_linked!(upb_Arena_New);
+        assert_linked!(upb_Arena_Free);
+        assert_linked!(upb_Arena_Malloc);
+        assert_linked!(upb_Arena_Realloc);
+        assert_linked!(upb_Arena_Fuse);
+        assert_linked!(upb_Arena_SpaceAllocated);
+    }
+
+    #[gtest]
+    fn raw_ffi_test() {
+        // SAFETY: FFI unit test uses C API under expected patterns.
+        unsafe {
+            let arena = upb_Arena_New().unwrap();
+            let bytes = upb_Arena_Malloc(arena, 3);
+            *bytes.add(2) = 7;
+            upb_Arena_Free(arena);
+        }
+    }
+
+    #[gtest]
+    fn test_arena_new_and_free() {
+        let arena = Arena::new();
+        drop(arena);
+    }
+
+    #[gtest]
+    fn test_as_upb_alloc() {
+        let arena = Arena::new();
+        let alloc = arena.as_upb_alloc();
+        assert!(!alloc.is_null());
+
+        // SAFETY: `alloc` is valid while `arena` is live.
+        unsafe {
+            let bytes = ((*alloc).func)(alloc, ptr::null_mut(), 0, 4);
+            assert!(!bytes.is_null());
+            *bytes.add(3) = 7;
+            assert!(((*alloc).func)(alloc, bytes, 4, 0).is_null());
+        }
+    }
+
+    #[gtest]
+    fn test_copy_integer_slices_with_endianness() {
+        let arena = Arena::new();
+        assert_eq!(
+            arena.copy_u32_slice_le_in(&[0x01020304, 0xAABBCCDD]).unwrap(),
+            &[0x04, 0x03, 0x02, 0x01, 0xDD, 0xCC, 0xBB, 0xAA]
+        );
+        assert_eq!(arena.copy_u32_slice_be_in(&[0x01020304]).unwrap(), &[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(
+            arena.copy_u64_slice_le_in(&[0x0102030405060708]).unwrap(),
+            &[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]
+        );
+        assert_eq!(
+            arena.copy_u64_slice_be_in(&[0x0102030405060708]).unwrap(),
+            &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]
+        );
+        assert_eq!(arena.copy_u32_slice_le_in(&[]).unwrap(), &[] as &[u8]);
+    }
+
+    #[gtest]
+    fn test_copy_u32_both_endian_in() {
+        let arena = Arena::new();
+        let (native, swapped) = arena.copy_u32_both_endian_in(&[0x01020304, 0xAABBCCDD]).unwrap();
+        assert_eq!(&native[..4], &0x01020304u32.to_ne_bytes());
+        assert_eq!(&swapped[4..], &0xDDCCBBAAu32.to_ne_bytes());
+        for (n, s) in native.chunks(4).zip(swapped.chunks(4)) {
+            assert_eq!(n.iter().rev().copied().collect::<Vec<_>>(), s);
+        }
+    }
+
+    #[gtest]
+    fn test_alloc_then_truncate() {
+        let arena = Arena::new();
+        // SAFETY: the decoder initializes the 3 elements it reports.
+        let decoded = unsafe {
+            arena.alloc_then_truncate::<u64>(8, |buf| {
+                for (i, x) in buf[..3].iter_mut().enumerate() {
+                    x.write(i as u64 + 1);
+                }
+                3
+            })
+        }
+        .unwrap();
+        assert_eq!(decoded, &[1, 2, 3]);
+
+        // The unused tail was given back, so the next allocation starts right
+        // after the decoded elements, without growing the arena.
+        let space_allocated = arena.space_allocated();
+        let next = arena.copy_in(&0u64).unwrap();
+        // With canaries, the trailing and leading canaries sit in between.
+        #[cfg(not(feature = "arena-canary"))]
+        assert_eq!(next as *const u64, decoded.as_ptr_range().end);
+        #[cfg(feature = "arena-canary")]
+        assert_eq!(next as *const u64, decoded.as_ptr_range().end.wrapping_add(2));
+        assert_eq!(arena.space_allocated(), space_allocated);
+    }
+
+    #[gtest]
+    fn test_copy_in_padded_struct_bytes() {
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        struct Padded {
+            a: u8,
+            // 3 bytes of padding
+            b: u32,
+        }
+
+        // Miri can not execute the upb arena itself since it is foreign code, but
+        // this only ever reads initialized bytes so it is clean under MSan.
+        let arena = Arena::new();
+        let copy = arena.copy_in(&Padded { a: 1, b: 0x02020202 }).unwrap();
+        assert_eq!((copy.a, copy.b), (1, 0x02020202));
+
+        let bytes = uninit_bytes_of(copy);
+        assert_eq!(bytes.len(), 8);
+        // SAFETY: bytes 0 and 4..8 are the initialized fields `a` and `b`.
+        unsafe {
+            assert_eq!(bytes[0].assume_init(), 1);
+            assert!(bytes[4..].iter().all(|b| b.assume_init() == 2));
+        }
+    }
+
+    #[gtest]
+    fn test_stats() {
+        let arena = Arena::new();
+        arena.checked_alloc(1024, 1).unwrap();
+        assert_eq!(
+            arena.stats(),
+            ArenaStats { space_allocated: arena.space_allocated(), fused_count: 1 }
+        );
+        let fused = arena.new_fused();
+        assert_eq!(fused.stats().fused_count, 2);
+    }
+
+    #[gtest]
+    fn test_merge_stats() {
+        let arenas = [Arena::new(), Arena::new(), Arena::new()];
+        for (i, arena) in arenas.iter().enumerate() {
+            arena.checked_alloc(1000 * (i + 1), 1).unwrap();
+        }
+        let merged = Arena::merge_stats(&arenas);
+        assert_eq!(merged.space_allocated, arenas.iter().map(Arena::space_allocated).sum());
+        assert!(merged.space_allocated >= 6000);
+        assert_eq!(merged.fused_count, 1);
+    }
+
+    #[gtest]
+    fn test_alloc_aligned() {
+        let arena = Arena::new();
+        for align in [1, 8, 16, 64, 256] {
+            arena.checked_alloc(1, 1).unwrap();
+            let alloc = arena.alloc_aligned(24, align).unwrap();
+            assert_eq!(alloc.len(), 24);
+            assert_eq!(alloc.as_ptr() as usize % align, 0);
+        }
+    }
+
+    #[gtest]
+    fn test_alloc_page_aligned() {
+        let arena = Arena::new();
+        let alloc = arena.alloc_page_aligned(5000).unwrap();
+        assert_eq!(alloc.as_ptr() as usize % 4096, 0);
+        assert_eq!(alloc.len(), 8192);
+        assert_eq!(arena.alloc_page_aligned(4096).unwrap().len(), 4096);
+    }
+
+    #[cfg(feature = "arena-tracking")]
+    #[gtest]
+    fn test_alignment_waste() {
+        let arena = Arena::new();
+        arena.checked_alloc(24, 8).unwrap();
+        assert_eq!(arena.alignment_waste(), 0);
+        for _ in 0..4 {
+            arena.alloc_aligned(24, 64).unwrap();
+        }
+        let waste = arena.alignment_waste();
+        assert!(waste > 0);
+        assert!(waste <= 4 * (64 - UPB_MALLOC_ALIGN));
+    }
+
+    #[cfg(feature = "arena-tracking")]
+    #[gtest]
+    fn test_allocation_histogram() {
+        let arena = Arena::new();
+        for size in [8, 9, 100] {
+            arena.checked_alloc(size, 1).unwrap();
+        }
+        arena.alloc_aligned(8, 64).unwrap();
+        let mut expected = [0; 32];
+        expected[3] = 2;
+        expected[4] = 1;
+        expected[7] = 1;
+        assert_eq!(arena.allocation_histogram(), expected);
+    }
+
+    #[gtest]
+    fn test_copy_utf8_lossy_in() {
+        let arena = Arena::new();
+        let bytes = b"ok \xF0\x9F\x92 then \xFF\xFE end \xE2\x82";
+        let repaired = arena.copy_utf8_lossy_in(bytes).unwrap();
+        assert_eq!(repaired, "ok \u{FFFD} then \u{FFFD}\u{FFFD} end \u{FFFD}");
+        assert_eq!(repaired, String::from_utf8_lossy(bytes));
+        assert_eq!(arena.copy_utf8_lossy_in("héllo".as_bytes()).unwrap(), "héllo");
+        assert_eq!(arena.copy_utf8_lossy_in(b"").unwrap(), "");
+    }
+
+    #[gtest]
+    fn test_copy_str_in_bounded() {
+        let arena = Arena::new();
+        assert_eq!(arena.copy_str_in_bounded("hello", 5), Ok("hello"));
+
+        let space_allocated = arena.space_allocated();
+        let huge = "x".repeat(1 << 20);
+        assert_eq!(
+            arena.copy_str_in_bounded(&huge, 1024),
+            Err(ArenaError::LengthExceeded(LengthExceeded { len: 1 << 20, max_len: 1024 }))
+        );
+        assert_eq!(arena.space_allocated(), space_allocated);
+    }
+
+    #[gtest]
+    fn test_copy_overlapping_in_from_same_arena() {
         let arena = Arena::new();
+        let first = arena.copy_slice_in(&[1u32, 2, 3, 4]).unwrap();
+        let second = arena.copy_overlapping_in(&first[1..]).unwrap();
+        assert_eq!(second, &[2, 3, 4]);
+        assert_eq!(first, &[1, 2, 3, 4]);
+    }
+
+    #[gtest]
+    fn test_dump_layout() {
+        let arena = Arena::new();
+        arena.copy_str_in("hello").unwrap();
+        arena.checked_alloc(10000, 1).unwrap();
+        let dump = arena.dump_layout();
+        assert!(dump.contains(&format!("{} bytes", arena.space_allocated())), "{dump}");
+        assert!(dump.contains("1 fused arena"), "{dump}");
+    }
+
+    #[gtest]
+    fn test_space_allocated_grows() {
+        let arena = Arena::new();
+        let before = arena.space_allocated();
+        arena.checked_alloc(1 << 16, 1).unwrap();
+        assert!(arena.space_allocated() >= before + (1 << 16));
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_validated() {
+        let arena = Arena::new();
+        let copy = arena.copy_slice_in_validated(&[1, 2, 3], |&x| x > 0).unwrap();
+        assert_eq!(copy, Ok(&[1, 2, 3][..]));
+
+        let copy = arena.copy_slice_in_validated(&[1, 2, -3, 4, -5], |&x| x > 0).unwrap();
+        assert_eq!(copy, Err(2));
+    }
+
+    #[gtest]
+    fn test_alloc_dyn_closure() {
+        let calls = core::cell::Cell::new(0);
+        let arena = Arena::new();
+        let f: &mut dyn FnMut() = arena.alloc_dyn(|| calls.set(calls.get() + 1), |f| f).unwrap();
+        f();
+        f();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[gtest]
+    fn test_alloc_framed() {
+        let arena = Arena::new();
+        let (header, body) = arena.alloc_framed(4, b"payload").unwrap();
+        assert_eq!(header.len(), 4);
+        assert_eq!(body, b"payload");
+        for (dst, src) in header.iter_mut().zip(7u32.to_be_bytes()) {
+            dst.write(src);
+        }
+        // The body is immediately after the header so the frame is contiguous.
+        assert_eq!(header.as_ptr_range().end.cast::<u8>(), body.as_ptr());
+        // SAFETY: the header and body are contiguous and were both initialized.
+        let frame = unsafe { slice::from_raw_parts(header.as_ptr().cast::<u8>(), 11) };
+        assert_eq!(frame, b"\0\0\0\x07payload");
+    }
+
+    #[gtest]
+    fn test_alloc_const_align() {
+        let arena = Arena::new();
+        let alloc = arena.alloc_const_align::<8>(24).unwrap();
+        assert_eq!(alloc.len(), 24);
+        assert_eq!(alloc.as_ptr() as usize % 8, 0);
+    }
+
+    #[gtest]
+    fn test_copy_large_slice_in() {
+        let arena = Arena::new();
+        let data: Vec<u8> = (0..4 << 20).map(|i: u32| (i % 251) as u8).collect();
+        let copy = arena.copy_slice_in(&data).unwrap();
+        assert_eq!(copy, &data[..]);
+        assert!(arena.space_allocated() >= data.len());
+    }
+
+    #[gtest]
+    fn test_alloc_pinned() {
+        struct SelfAddressed {
+            addr: usize,
+            _pin: core::marker::PhantomPinned,
+        }
+
+        let arena = Arena::new();
+        let mut pinned = arena
+            .alloc_pinned(SelfAddressed { addr: 0, _pin: core::marker::PhantomPinned })
+            .unwrap();
+        let addr = &*pinned as *const SelfAddressed as usize;
+        // SAFETY: `addr` is a plain field that is not structurally pinned.
+        unsafe { pinned.as_mut().get_unchecked_mut().addr = addr };
+
+        arena.copy_slice_in(&[0u8; 1 << 16]).unwrap();
+        assert_eq!(pinned.addr, addr);
+        assert_eq!(&*pinned as *const SelfAddressed as usize, addr);
+    }
+
+    #[gtest]
+    fn test_copy_init_slice_in() {
+        let arena = Arena::new();
+        let mut data = [MaybeUninit::<u16>::uninit(); 4];
+        for (i, x) in data.iter_mut().enumerate() {
+            x.write(i as u16 * 10);
+        }
+        // SAFETY: every element of `data` was initialized above.
+        let copy = unsafe { arena.copy_init_slice_in(&data) }.unwrap();
+        assert_eq!(copy, &[0, 10, 20, 30]);
+    }
+
+    #[gtest]
+    fn test_new_fused_shares_lifetime() {
+        let arena = Arena::new();
+        let data = arena.copy_str_in("hello").unwrap() as *const str;
+        let fused = arena.new_fused();
+        drop(arena);
+        // SAFETY: `data` is kept alive by `fused`.
+        assert_eq!(unsafe { &*data }, "hello");
+        drop(fused);
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_chunked() {
+        let arena = Arena::new();
+        let data: Vec<u32> = (0..10).collect();
+        let mut calls = 0;
+        let copy = arena.copy_slice_in_chunked(&data, 3, || {
+            calls += 1;
+            true
+        });
+        assert_eq!(copy.unwrap(), &data[..]);
+        assert_eq!(calls, 3);
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_chunked_cancelled() {
+        let arena = Arena::new();
+        let data: Vec<u32> = (0..10).collect();
+        assert_eq!(arena.copy_slice_in_chunked(&data, 4, || false), None);
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_progress() {
+        let arena = Arena::new();
+        let data: Vec<u32> = (0..1000).collect();
+        let mut reports = Vec::new();
+        let copy = arena.copy_slice_in_progress(&data, 100, |done, total| {
+            reports.push((done, total));
+        });
+        assert_eq!(copy.unwrap(), &data[..]);
+        assert_eq!(reports.len(), 10);
+        assert_eq!(reports[0], (100, 1000));
+        assert_eq!(reports.last(), Some(&(1000, 1000)));
+    }
+
+    #[gtest]
+    fn test_new_prefaulted() {
+        let arena = Arena::new_prefaulted(1 << 16);
+        let before = arena.space_allocated();
+        assert!(before >= 1 << 16);
+        // The prefaulted block is reused rather than grown.
+        arena.copy_slice_in(&[0u8; 1 << 15]).unwrap();
+        assert_eq!(arena.space_allocated(), before);
+    }
+
+    #[gtest]
+    fn test_copy_enum_in() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(i32)]
+        enum Color {
+            Red = 1,
+            Green = 2,
+            Blue = 3,
+        }
+        let arena = Arena::new();
+        // SAFETY: `Color` is `repr(i32)` and 1..=3 are all of its discriminants.
+        unsafe {
+            assert_eq!(arena.copy_enum_in::<Color, 1, 3>(1), Some(&Color::Red));
+            assert_eq!(arena.copy_enum_in::<Color, 1, 3>(2), Some(&Color::Green));
+            assert_eq!(arena.copy_enum_in::<Color, 1, 3>(3), Some(&Color::Blue));
+            assert_eq!(arena.copy_enum_in::<Color, 1, 3>(0), None);
+            assert_eq!(arena.copy_enum_in::<Color, 1, 3>(4), None);
+            assert_eq!(arena.copy_enum_in::<Color, 1, 3>(-1), None);
+        }
+    }
+
+    #[cfg(feature = "arena-provenance")]
+    #[gtest]
+    fn test_last_alloc_site() {
+        let arena = Arena::new();
+        assert_eq!(arena.last_alloc_site(), None);
+        let line = line!() + 1;
+        arena.copy_slice_in(&[1u8, 2, 3]).unwrap();
+        let site = arena.last_alloc_site().unwrap();
+        assert_eq!(site.file(), file!());
+        assert_eq!(site.line(), line);
+
+        let line = line!() + 1;
+        arena.copy_str_in("hello").unwrap();
+        assert_eq!(arena.last_alloc_site().unwrap().line(), line);
+    }
+
+    #[gtest]
+    fn test_with_copy_in() {
+        let arena = Arena::new();
+        let data = [3u32, 4];
+        let norm = arena.with_copy_in(&data, |copy| {
+            assert_ne!(copy.as_ptr(), data.as_ptr());
+            copy[0] * copy[0] + copy[1] * copy[1]
+        });
+        assert_eq!(norm, Some(25));
+    }
+
+    #[cfg(feature = "arena-pressure")]
+    #[gtest]
+    fn test_new_with_threshold() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let arena = Arena::new_with_threshold(
+            4096,
+            Box::new({
+                let calls = Arc::clone(&calls);
+                move |space_allocated| {
+                    assert!(space_allocated > 4096);
+                    calls.fetch_add(1, Ordering::Relaxed);
+                }
+            }),
+        );
+        arena.copy_slice_in(&[0u8; 16]).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+        for _ in 0..4 {
+            arena.copy_slice_in(&[0u8; 4096]).unwrap();
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[gtest]
+    fn test_copy_sorted_in() {
+        let arena = Arena::new();
+        let data = [3, 1, 2];
+        assert_eq!(arena.copy_sorted_in(&data).unwrap(), &[1, 2, 3]);
+        assert_eq!(data, [3, 1, 2]);
+        assert_eq!(arena.copy_sorted_by_in(&data, |a, b| b.cmp(a)).unwrap(), &[3, 2, 1]);
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_checked() {
+        // Bitwise CRC-32, independent of the table used by the arena.
+        fn crc32(data: &[u8]) -> u32 {
+            let mut crc = !0u32;
+            for &byte in data {
+                crc ^= u32::from(byte);
+                for _ in 0..8 {
+                    crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+                }
+            }
+            !crc
+        }
+
+        let arena = Arena::new();
+        let (copy, crc) = arena.copy_slice_in_checked(b"123456789").unwrap();
+        assert_eq!(copy, b"123456789");
+        assert_eq!(crc, 0xCBF4_3926);
+
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let (copy, crc) = arena.copy_slice_in_checked(&data).unwrap();
+        assert_eq!(copy, &data[..]);
+        assert_eq!(crc, crc32(&data));
+
+        assert_eq!(arena.copy_slice_in_checked(&[]).unwrap(), (&[][..], 0));
+    }
+
+    #[gtest]
+    fn test_copy_option_in() {
+        use core::num::NonZeroU32;
+
+        assert_eq!(size_of::<Option<NonZeroU32>>(), 4);
+        let arena = Arena::new();
+        let some = arena.copy_option_in(NonZeroU32::new(7)).unwrap();
+        assert_eq!(*some, NonZeroU32::new(7));
+        assert_eq!(size_of_val(some), 4);
+        let none = arena.copy_option_in::<NonZeroU32>(None).unwrap();
+        assert_eq!(*none, None);
+        assert_eq!(*arena.copy_option_in(Some(1u8)).unwrap(), Some(1));
+    }
+
+    #[gtest]
+    fn test_copy_packed_in() {
+        #[derive(Clone, Copy)]
+        #[repr(C, packed)]
+        struct Header {
+            tag: u8,
+            len: u32,
+            crc: u16,
+        }
+
+        let arena = Arena::new();
+        let packed = [Header { tag: 1, len: 0x0102_0304, crc: 0xBEEF }; 2];
+        let copy = arena.copy_packed_in(packed[1]).unwrap();
+        assert_eq!((copy as *const Header as usize) % UPB_MALLOC_ALIGN, 0);
+        // Braces copy each field out rather than referencing it.
+        assert_eq!(({ copy.tag }, { copy.len }, { copy.crc }), (1, 0x0102_0304, 0xBEEF));
+    }
+
+    #[gtest]
+    fn test_copy_soa_in() {
+        let arena = Arena::new();
+        let pairs = [(1u8, 10u64), (2, 20), (3, 30)];
+        let (keys, values) = arena.copy_soa_in(&pairs).unwrap();
+        assert_eq!(keys, &[1, 2, 3]);
+        assert_eq!(values, &[10, 20, 30]);
+        for (i, &(k, v)) in pairs.iter().enumerate() {
+            assert_eq!((keys[i], values[i]), (k, v));
+        }
+
+        let (keys, values) = arena.copy_soa_in::<u8, u64>(&[]).unwrap();
+        assert!(keys.is_empty() && values.is_empty());
+    }
+
+    #[gtest]
+    fn test_copy_small_in() {
+        let arena = Arena::new();
+        assert_eq!(arena.copy_small_in(&0x0123_4567_89AB_CDEFu64), Some(&0x0123_4567_89AB_CDEF));
+        assert_eq!(arena.copy_small_in(&[7u8; 64]).unwrap(), &[7u8; 64]);
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_rev() {
+        let arena = Arena::new();
+        let data = [1, 2, 3, 4];
+        let (forward, reversed) = arena.copy_slice_in_rev(&data).unwrap();
+        assert_eq!(forward, &data);
+        assert_eq!(reversed.copied().collect::<Vec<_>>(), [4, 3, 2, 1]);
+    }
+
+    #[gtest]
+    fn test_on_handle_drop_hooks_run_lifo() {
+        use std::sync::{Arc, Mutex};
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let arena = Arena::new();
+        for i in 0..2 {
+            let order = Arc::clone(&order);
+            arena.on_handle_drop(Box::new(move || order.lock().unwrap().push(i)));
+        }
+        assert!(order.lock().unwrap().is_empty());
         drop(arena);
+        assert_eq!(*order.lock().unwrap(), [1, 0]);
+    }
+
+    #[gtest]
+    fn test_on_handle_drop_runs_while_fused() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let a = Arena::new();
+        let b = Arena::new();
+        a.fuse(&b);
+        let data = a.copy_slice_in(b"still live").unwrap().as_ptr();
+        let flag = Arc::clone(&ran);
+        a.on_handle_drop(Box::new(move || flag.store(true, Ordering::Relaxed)));
+        drop(a);
+        assert!(ran.load(Ordering::Relaxed));
+        // SAFETY: `a`'s memory is kept alive by `b`, to which it was fused.
+        assert_eq!(unsafe { slice::from_raw_parts(data, 10) }, b"still live");
+    }
+
+    #[cfg(feature = "arena-canary")]
+    #[gtest]
+    fn test_verify_canaries() {
+        let arena = Arena::new();
+        let data = arena.checked_alloc(3, 1).unwrap().as_mut_ptr().cast::<u8>();
+        arena.copy_str_in("hello").unwrap();
+        assert!(arena.verify_canaries());
+
+        // Simulate FFI code writing one byte past the end of `data`.
+        // SAFETY: the byte past `data` is the trailing canary, which is still
+        // memory owned by the arena.
+        unsafe { data.add(3).write(0) };
+        assert!(!arena.verify_canaries());
+    }
+
+    #[gtest]
+    fn test_assert_no_alias_disjoint() {
+        let arena = Arena::new();
+        let a = arena.copy_slice_in(&[1u32, 2, 3]).unwrap();
+        let b = arena.copy_slice_in(&[4u32, 5]).unwrap();
+        assert_no_alias(a, b);
+        assert_no_alias(&a[..1], &a[1..]);
+        assert_no_alias(a, &a[..0]);
+    }
+
+    #[gtest]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overlaps read-only region")]
+    fn test_shrink_over_readonly_panics() {
+        let arena = Arena::new();
+        let constants = arena.copy_slice_in_readonly(&[1u64, 2]).unwrap();
+        // SAFETY: this deliberately breaks the contract by releasing `constants`
+        // while it is still referenced, which is what the check catches.
+        unsafe { arena.shrink_last(NonNull::from(constants).cast(), 16, 8) };
+    }
+
+    #[gtest]
+    fn test_shrink_beside_readonly() {
+        let arena = Arena::new();
+        let constants = arena.copy_slice_in_readonly(&[1u32, 2, 3]).unwrap();
+        let scratch = arena.copy_slice_in_mut(&[4u32, 5]).unwrap();
+        // SAFETY: `scratch` is the most recent allocation and is not used again.
+        unsafe { arena.shrink_last(NonNull::from(scratch).cast(), 8, 4) };
+        arena.checked_alloc(64, 8).unwrap();
+        assert_eq!(constants, &[1, 2, 3]);
+    }
+
+    #[gtest]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "slices alias")]
+    fn test_assert_no_alias_overlapping() {
+        let arena = Arena::new();
+        let a = arena.copy_slice_in(&[1u32, 2, 3, 4]).unwrap();
+        assert_no_alias(&a[..3], &a[2..]);
+    }
+
+    #[gtest]
+    fn test_copy_argv_in() {
+        use core::ffi::CStr;
+
+        let arena = Arena::new();
+        let argv = arena.copy_argv_in(&["prog", "--flag"]).unwrap().unwrap();
+        assert_eq!(argv.len(), 3);
+        assert!(argv[2].is_null());
+        // SAFETY: the non-null entries point to NUL-terminated strings.
+        unsafe {
+            assert_eq!(CStr::from_ptr(argv[0]).to_str(), Ok("prog"));
+            assert_eq!(CStr::from_ptr(argv[1]).to_str(), Ok("--flag"));
+        }
+    }
+
+    #[gtest]
+    fn test_copy_argv_in_interior_nul() {
+        let arena = Arena::new();
+        assert_eq!(
+            arena.copy_argv_in(&["prog", "a\0b"]),
+            Err(InteriorNul { index: 1, position: 1 })
+        );
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_minmax() {
+        let arena = Arena::new();
+        let (copy, range) = arena.copy_slice_in_minmax(&[3, 1, 5, 2]).unwrap();
+        assert_eq!(copy, &[3, 1, 5, 2]);
+        assert_eq!(range, Some((1, 5)));
+
+        let (copy, range) = arena.copy_slice_in_minmax::<f64>(&[]).unwrap();
+        assert!(copy.is_empty());
+        assert_eq!(range, None);
+    }
+
+    #[gtest]
+    fn test_copy_transform_in_reuses_scratch() {
+        let arena = Arena::new();
+        let mut scratch = Vec::new();
+        let doubled = arena.copy_transform_in(&[1, 2, 3], &mut scratch, |x| x * 2).unwrap();
+        let capacity = scratch.capacity();
+        let negated = arena.copy_transform_in(&[4, 5], &mut scratch, |x| -x).unwrap();
+        assert_eq!(doubled, &[2, 4, 6]);
+        assert_eq!(negated, &[-4, -5]);
+        assert_eq!(scratch.capacity(), capacity);
+    }
+
+    #[gtest]
+    fn test_copy_cursor_remainder_in() {
+        let arena = Arena::new();
+        let buf = [1u8, 2, 3, 4, 5, 6];
+        let mut cursor = std::io::Cursor::new(&buf[..]);
+        cursor.set_position(2);
+        assert_eq!(arena.copy_cursor_remainder_in(&mut cursor).unwrap(), &[3, 4, 5, 6]);
+        assert_eq!(cursor.position(), 6);
+
+        cursor.set_position(10);
+        assert_eq!(arena.copy_cursor_remainder_in(&mut cursor).unwrap(), &[]);
+        assert_eq!(cursor.position(), 10);
+    }
+
+    #[gtest]
+    fn test_alloc_atomic_u32_shared_across_threads() {
+        use core::sync::atomic::Ordering;
+
+        let arena = Arena::new();
+        let counter = arena.alloc_atomic_u32(5).unwrap();
+        std::thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        assert_eq!(counter.load(Ordering::Relaxed), 2005);
+        assert_eq!(counter as *const AtomicU32 as usize % align_of::<AtomicU32>(), 0);
+        assert_eq!(arena.alloc_atomic_u64(u64::MAX).unwrap().load(Ordering::Relaxed), u64::MAX);
+    }
+
+    #[gtest]
+    fn test_copy_union_as_in() {
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        union Value {
+            int: u32,
+            bytes: [u8; 8],
+        }
+
+        let arena = Arena::new();
+        let value = Value { bytes: [0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0] };
+        // SAFETY: all bytes of `value` are initialized and any 4 bytes are a valid u32.
+        let int: &u32 = unsafe { arena.copy_union_as_in(&value) }.unwrap();
+        assert_eq!(*int, u32::from_le_bytes([0x78, 0x56, 0x34, 0x12]));
+    }
+
+    #[gtest]
+    #[should_panic(expected = "alloc_pinned::<")]
+    fn test_alloc_pinned_over_aligned_panics() {
+        #[repr(align(64))]
+        struct OverAligned;
+        Arena::new().alloc_pinned(OverAligned);
+    }
+
+    #[gtest]
+    #[should_panic(expected = "variant is larger than the union")]
+    fn test_copy_union_as_in_larger_variant() {
+        let arena = Arena::new();
+        // SAFETY: the size check panics before anything is read.
+        let _: Option<&u64> = unsafe { arena.copy_union_as_in(&0u32) };
+    }
+
+    #[cfg(feature = "arena-shadow")]
+    #[gtest]
+    fn test_shadow_log() {
+        fn init(bytes: &[MaybeUninit<u8>]) -> Vec<u8> {
+            // SAFETY: only integers, which have no padding, are passed in.
+            bytes.iter().map(|b| unsafe { b.assume_init() }).collect()
+        }
+        take_shadow_log();
+        let arena = Arena::new();
+        arena.copy_slice_in(b"hello").unwrap();
+        arena.copy_in(&[1u16, 2]).unwrap();
+        arena.copy_slice_in_checked(b"crc").unwrap();
+        arena.copy_sorted_in(&[2u8, 1]).unwrap();
+        // SAFETY: the uninitialized allocation is never read.
+        unsafe { arena.alloc(8, 1) }.unwrap();
+        let log = take_shadow_log();
+        assert_eq!(log.len(), 4);
+        assert_eq!(init(&log[0]), b"hello");
+        assert_eq!(init(&log[1]), [1u16.to_ne_bytes(), 2u16.to_ne_bytes()].concat());
+        assert_eq!(init(&log[2]), b"crc");
+        assert_eq!(init(&log[3]), [2, 1]);
+        assert!(take_shadow_log().is_empty());
+
+        // Types with padding are logged too, padding bytes and all.
+        arena.copy_slice_in(&[(1i32, 2usize)]).unwrap();
+        let log = take_shadow_log();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].len(), size_of::<(i32, usize)>());
+    }
+
+    #[cfg(feature = "arena-deterministic")]
+    #[gtest]
+    fn test_snapshot_is_reproducible() {
+        fn build() -> Vec<u8> {
+            let arena = Arena::new();
+            arena.copy_slice_in(b"name").unwrap();
+            arena.copy_in(&0x1234u32).unwrap();
+            arena.checked_alloc(3, 1).unwrap();
+            arena.alloc_aligned(8, 64).unwrap();
+            // SAFETY: only integers, which have no padding, were copied in.
+            unsafe { arena.snapshot() }
+        }
+        let snapshot = build();
+        assert_eq!(snapshot, build());
+        assert_eq!(&snapshot[..12], b"\x04\0\0\0\0\0\0\0name");
+        assert_eq!(snapshot.len(), (8 + 4) + (8 + 4) + (8 + 3) + (8 + 8));
+    }
+
+    #[gtest]
+    fn test_lazy_copy_in_copies_only_consumed() {
+        // Larger than upb's largest block, so each copy gets a block of its own.
+        const SIZE: usize = 64 << 10;
+        let data = [[1u8; SIZE], [2; SIZE], [3; SIZE], [4; SIZE], [5; SIZE]];
+        let arena = Arena::new();
+        let before = arena.space_allocated();
+        let consumed: Vec<&[u8; SIZE]> = arena.lazy_copy_in(&data).take(2).collect();
+        assert_eq!(consumed[0][0], 1);
+        assert_eq!(consumed[1][0], 2);
+        assert!(!ptr::eq(consumed[0], &data[0]));
+        let grown = arena.space_allocated() - before;
+        assert!((2 * SIZE..3 * SIZE).contains(&grown), "{grown}");
+    }
+
+    #[gtest]
+    fn test_alloc_default_slice() {
+        #[derive(Clone, Copy, Default, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let arena = Arena::new();
+        let points = arena.alloc_default_slice::<Point>(8).unwrap();
+        assert_eq!(points.len(), 8);
+        assert_eq!(points[0], Point::default());
+        assert_eq!(points[7], Point { x: 0, y: 0 });
+        points[3].y = 5;
+        assert_eq!(points[3], Point { x: 0, y: 5 });
+        assert_eq!(points[4], Point::default());
+        assert!(arena.alloc_default_slice::<u64>(usize::MAX).is_none());
+    }
+
+    #[gtest]
+    fn test_copy_stable_sorted_in() {
+        let arena = Arena::new();
+        let data = [(3, 'a'), (1, 'b'), (3, 'c'), (2, 'd'), (1, 'e')];
+        let (sorted, permutation) = arena.copy_stable_sorted_in(&data, |&(k, _)| k).unwrap();
+        assert_eq!(sorted, &[(1, 'b'), (1, 'e'), (2, 'd'), (3, 'a'), (3, 'c')]);
+        assert_eq!(permutation, &[1, 4, 3, 0, 2]);
+        for (value, &i) in sorted.iter().zip(permutation) {
+            assert_eq!(*value, data[i as usize]);
+        }
+        assert_eq!(data[0], (3, 'a'));
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_surviving() {
+        let a = Arena::new();
+        let b = Arena::new();
+        let copy = Arena::copy_slice_in_surviving(&a, &b, &[1, 2, 3]).unwrap();
+        assert_eq!(a.stats().fused_count, 2);
+        drop(a);
+        assert_eq!(copy, &[1, 2, 3]);
+    }
+
+    #[gtest]
+    fn test_writes_visible_after_send() {
+        let arena = Arena::new();
+        let addr = arena.copy_slice_in(&[7u64, 8, 9]).unwrap().as_ptr() as usize;
+        let (tx, rx) = std::sync::mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            let arena: Arena = rx.recv().unwrap();
+            // SAFETY: `addr` is a slice of 3 u64s in `arena`, which is alive.
+            let copy = unsafe { slice::from_raw_parts(addr as *const u64, 3) };
+            let sum: u64 = copy.iter().sum();
+            drop(arena);
+            sum
+        });
+        tx.send(arena).unwrap();
+        assert_eq!(reader.join().unwrap(), 24);
+    }
+
+    #[gtest]
+    fn test_fuse_or_copy() {
+        let arena = Arena::new();
+        let other = Arena::new();
+        let data = other.copy_slice_in(&[1, 2, 3]).unwrap();
+        // SAFETY: `data` was allocated from `other`.
+        let fused = unsafe { arena.fuse_or_copy(&other, data) }.unwrap();
+        assert_eq!(fused.as_ptr(), data.as_ptr());
+
+        let mut block = [0u64; 128];
+        // SAFETY: `block` outlives the arena, which has no allocator to grow with.
+        let raw = unsafe {
+            upb_Arena_Init(block.as_mut_ptr().cast(), size_of_val(&block), ptr::null_mut())
+        };
+        // SAFETY: `raw` is a valid arena; it is never freed, since it owns no memory.
+        let initial_block = core::mem::ManuallyDrop::new(unsafe { Arena::from_raw(raw.unwrap()) });
+        let data = initial_block.copy_slice_in(&[4, 5]).unwrap();
+        assert!(!arena.try_fuse(&initial_block));
+        // SAFETY: `data` was allocated from `initial_block`.
+        let copy = unsafe { arena.fuse_or_copy(&initial_block, data) }.unwrap();
+        assert_eq!(copy, &[4, 5]);
+        assert_ne!(copy.as_ptr(), data.as_ptr());
+    }
+
+    #[gtest]
+    fn test_copy_slice_in_auto() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(align(64))]
+        struct CacheLine(u32);
+
+        let arena = Arena::new();
+        assert_eq!(arena.copy_slice_in_auto(&[1u64, 2, 3]).unwrap(), &[1, 2, 3]);
+        let lines = arena.copy_slice_in_auto(&[CacheLine(1), CacheLine(2)]).unwrap();
+        assert_eq!(lines, &[CacheLine(1), CacheLine(2)]);
+        assert_eq!(lines.as_ptr() as usize % 64, 0);
+    }
+
+    #[gtest]
+    fn test_try_copy_slice_in() {
+        #[derive(Clone, Copy)]
+        #[repr(align(16))]
+        struct Wide;
+
+        let arena = Arena::new();
+        assert_eq!(arena.try_copy_slice_in(&[1u32, 2]), Ok(&[1u32, 2][..]));
+        assert_eq!(arena.try_copy_slice_in(&[Wide]).err(), Some(ArenaError::AlignmentTooLarge));
+
+        unsafe extern "C" fn fail_alloc(
+            _alloc: *mut upb_alloc,
+            _ptr: *mut u8,
+            _oldsize: usize,
+            _size: usize,
+        ) -> *mut u8 {
+            ptr::null_mut()
+        }
+        let mut alloc = upb_alloc { func: fail_alloc };
+        let mut block = [0u64; 64];
+        // SAFETY: `block` and `alloc` outlive the arena, which can not grow past
+        // `block` since `alloc` always fails.
+        let raw =
+            unsafe { upb_Arena_Init(block.as_mut_ptr().cast(), size_of_val(&block), &mut alloc) };
+        // SAFETY: `raw` is a valid arena; it is never freed, since it owns no memory.
+        let fixed = core::mem::ManuallyDrop::new(unsafe { Arena::from_raw(raw.unwrap()) });
+        assert_eq!(fixed.try_copy_slice_in(&[0u8; 4096]), Err(ArenaError::OutOfMemory));
+    }
+
+    #[gtest]
+    fn test_extend_in_caller_arena() {
+        fn assemble<'a>(arena: &'a Arena, fields: &[u32]) -> Option<&'a [u32]> {
+            let header = [fields.len() as u32];
+            Arena::extend_in(arena, &header, fields)
+        }
+
+        let arena = Arena::new();
+        let assembled = assemble(&arena, &[7, 8, 9]).unwrap();
+        assert_eq!(assembled, &[3, 7, 8, 9]);
+        assert_eq!(Arena::extend_in::<u8>(&arena, &[], &[]).unwrap(), &[]);
+    }
+
+    #[gtest]
+    fn test_over_aligned_panic_names_type() {
+        #[derive(Clone, Copy)]
+        #[repr(align(32))]
+        struct OverAligned;
+
+        let panic =
+            std::panic::catch_unwind(|| Arena::new().copy_slice_in(&[OverAligned]).is_some())
+                .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.contains("copy_slice_in::<"), "{message}");
+        assert!(message.contains("OverAligned>: align 32 exceeds UPB_MALLOC_ALIGN 8"), "{message}");
+
+        let panic =
+            std::panic::catch_unwind(|| Arena::new().copy_in(&OverAligned).is_some()).unwrap_err();
+        assert!(panic.downcast_ref::<String>().unwrap().contains("copy_in::<"));
     }
 }
@@ -0,0 +1,94 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::cell::Cell;
+use core::mem::{align_of, size_of};
+use core::ptr::NonNull;
+
+/// Number of values in the first slab; later slabs double up to `MAX_SLAB`.
+const MIN_SLAB: usize = 8;
+const MAX_SLAB: usize = 1024;
+
+/// A typed arena of `T` values which bump-allocates slabs of `T` from a upb
+/// Arena, amortizing the per-allocation overhead over many values.
+#[derive(Debug)]
+pub struct ArenaArena<'a, T> {
+    arena: &'a Arena,
+    next: Cell<NonNull<T>>,
+    remaining: Cell<usize>,
+    slab_len: Cell<usize>,
+}
+
+impl Arena {
+    /// Returns a typed arena which allocates `T` values in slabs from this
+    /// arena.
+    pub fn typed_arena<T: Copy>(&self) -> ArenaArena<'_, T> {
+        ArenaArena {
+            arena: self,
+            next: Cell::new(NonNull::dangling()),
+            remaining: Cell::new(if size_of::<T>() == 0 { usize::MAX } else { 0 }),
+            slab_len: Cell::new(MIN_SLAB / 2),
+        }
+    }
+}
+
+impl<'a, T: Copy> ArenaArena<'a, T> {
+    /// Moves `value` into the current slab and returns a reference to it,
+    /// starting a new slab if the current one is full. Returns None if the
+    /// allocation of a new slab failed.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> Option<&'a mut T> {
+        if self.remaining.get() == 0 {
+            let slab_len = (self.slab_len.get() * 2).min(MAX_SLAB);
+            let slab = self.arena.alloc_aligned(size_of::<T>() * slab_len, align_of::<T>())?;
+            self.next.set(NonNull::from(slab).cast());
+            self.remaining.set(slab_len);
+            self.slab_len.set(slab_len);
+        }
+        let ptr = self.next.get();
+        // SAFETY:
+        // - `ptr` is aligned for `T` and the current slab has room for `self.remaining`
+        //   more values, of which `ptr` is the first.
+        // - no other reference to that slot exists, since `next` moves past it.
+        unsafe {
+            self.next.set(NonNull::new_unchecked(ptr.as_ptr().add(1)));
+            self.remaining.set(self.remaining.get() - 1);
+            ptr.as_ptr().write(value);
+            Some(&mut *ptr.as_ptr())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_typed_arena_alloc() {
+        let arena = Arena::new();
+        let typed = arena.typed_arena::<(u32, u64)>();
+        let handles: Vec<&mut (u32, u64)> =
+            (0..1000).map(|i| typed.alloc((i, u64::from(i) * 3)).unwrap()).collect();
+        for (i, handle) in handles.iter().enumerate() {
+            assert_eq!(**handle, (i as u32, i as u64 * 3));
+        }
+        for handle in handles {
+            handle.1 = 0;
+        }
+    }
+
+    #[gtest]
+    fn test_typed_arena_zero_sized() {
+        let arena = Arena::new();
+        let typed = arena.typed_arena::<()>();
+        for _ in 0..10 {
+            assert_eq!(typed.alloc(()), Some(&mut ()));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::mem::MaybeUninit;
+
+/// A slice of bools held in a upb Arena, packed 8 to a byte.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaBitset<'a> {
+    bytes: &'a [u8],
+    len: usize,
+}
+
+impl Arena {
+    /// Copies the bools into this arena packed as bits, with bit `i % 8` of
+    /// byte `i / 8` holding `bits[i]`. Returns None if the allocation failed.
+    pub fn copy_bools_in<'a>(&'a self, bits: &[bool]) -> Option<ArenaBitset<'a>> {
+        let len = bits.len();
+        let bytes = self.checked_alloc(len.div_ceil(8), 1)?;
+        for (byte, chunk) in bytes.iter_mut().zip(bits.chunks(8)) {
+            let packed =
+                chunk.iter().enumerate().fold(0u8, |b, (i, &bit)| b | (u8::from(bit) << i));
+            byte.write(packed);
+        }
+        // SAFETY: every byte was written above, since there are exactly
+        // `bytes.len()` chunks.
+        let bytes = unsafe { &*(bytes as *const [MaybeUninit<u8>] as *const [u8]) };
+        Some(ArenaBitset { bytes, len })
+    }
+}
+
+impl<'a> ArenaBitset<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bool at index `i`.
+    ///
+    /// Panics if `i >= self.len()`.
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "index out of bounds");
+        self.bytes[i / 8] & (1 << (i % 8)) != 0
+    }
+
+    /// Returns the packed bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + 'a {
+        let bitset = *self;
+        (0..self.len).map(move |i| bitset.get(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_bools_in() {
+        let arena = Arena::new();
+        let bits: Vec<bool> = (0..20).map(|i| i % 3 == 0).collect();
+        let bitset = arena.copy_bools_in(&bits).unwrap();
+        assert_eq!(bitset.len(), 20);
+        assert_eq!(bitset.as_bytes().len(), 3);
+        for (i, &bit) in bits.iter().enumerate() {
+            assert_eq!(bitset.get(i), bit, "bit {i}");
+        }
+        assert_eq!(bitset.iter().collect::<Vec<_>>(), bits);
+    }
+
+    #[gtest]
+    fn test_copy_empty_bools_in() {
+        let arena = Arena::new();
+        let bitset = arena.copy_bools_in(&[]).unwrap();
+        assert!(bitset.is_empty());
+        assert!(bitset.as_bytes().is_empty());
+    }
+
+    #[gtest]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_bitset_get_out_of_bounds() {
+        let arena = Arena::new();
+        // Bit 3 is padding in the packed byte, not an element.
+        arena.copy_bools_in(&[true, true, true]).unwrap().get(3);
+    }
+}
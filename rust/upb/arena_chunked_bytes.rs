@@ -0,0 +1,121 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+
+/// A node in the linked list of an `ArenaChunkedBytes`, itself held in the
+/// arena.
+#[derive(Clone, Copy)]
+struct ChunkNode<'a> {
+    data: &'a [u8],
+    next: Option<&'a ChunkNode<'a>>,
+}
+
+/// Bytes held in a upb Arena as a linked list of separately allocated chunks,
+/// rather than as one contiguous allocation.
+#[derive(Clone, Copy)]
+pub struct ArenaChunkedBytes<'a> {
+    head: Option<&'a ChunkNode<'a>>,
+    len: usize,
+}
+
+impl Arena {
+    /// Copies the bytes into this arena as a linked list of chunks of at most
+    /// `chunk_size` bytes each. Returns None if an allocation failed.
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn copy_chunked_in<'a>(
+        &'a self,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> Option<ArenaChunkedBytes<'a>> {
+        assert!(chunk_size > 0, "chunk_size must be non-zero");
+        // Built back to front so that each node can point at its already-built
+        // successor.
+        let mut head = None;
+        for chunk in data.chunks(chunk_size).rev() {
+            let node = ChunkNode { data: self.copy_slice_in(chunk)?, next: head };
+            head = Some(self.copy_in(&node)?);
+        }
+        Some(ArenaChunkedBytes { head, len: data.len() })
+    }
+}
+
+impl<'a> ArenaChunkedBytes<'a> {
+    /// Total number of bytes across all chunks.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the chunks in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &'a [u8]> {
+        let mut node = self.head;
+        core::iter::from_fn(move || {
+            let current = node?;
+            node = current.next;
+            Some(current.data)
+        })
+    }
+
+    /// Returns the byte at `offset`.
+    ///
+    /// This walks the list, so is linear in the number of chunks. Panics if
+    /// `offset >= self.len()`.
+    pub fn bytes_at(&self, offset: usize) -> u8 {
+        assert!(offset < self.len, "offset {offset} out of bounds for length {}", self.len);
+        let mut offset = offset;
+        for chunk in self.chunks() {
+            if let Some(&byte) = chunk.get(offset) {
+                return byte;
+            }
+            offset -= chunk.len();
+        }
+        unreachable!("chunk lengths sum to self.len")
+    }
+}
+
+impl core::fmt::Debug for ArenaChunkedBytes<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_list().entries(self.chunks()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_chunked_in() {
+        let arena = Arena::new();
+        let data: Vec<u8> = (0..10 * 1024).map(|i: u32| (i % 251) as u8).collect();
+        let chunked = arena.copy_chunked_in(&data, 1024).unwrap();
+        assert_eq!(chunked.len(), data.len());
+        assert_eq!(chunked.chunks().count(), 10);
+        assert!(chunked.chunks().all(|c| c.len() == 1024));
+        assert_eq!(chunked.chunks().flatten().copied().collect::<Vec<_>>(), data);
+        for offset in [0, 1, 1023, 1024, 5000, 10 * 1024 - 1] {
+            assert_eq!(chunked.bytes_at(offset), data[offset]);
+        }
+    }
+
+    #[gtest]
+    fn test_copy_chunked_in_uneven_and_empty() {
+        let arena = Arena::new();
+        let chunked = arena.copy_chunked_in(b"abcde", 2).unwrap();
+        assert_eq!(chunked.chunks().collect::<Vec<_>>(), [&b"ab"[..], b"cd", b"e"]);
+        assert_eq!(chunked.bytes_at(4), b'e');
+
+        let empty = arena.copy_chunked_in(b"", 2).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.chunks().count(), 0);
+    }
+}
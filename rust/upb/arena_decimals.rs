@@ -0,0 +1,66 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+
+/// Fixed-point decimals held in a upb Arena as scaled integers sharing one
+/// scale, so that value `v` with scale `s` stands for `v / 10^s`.
+///
+/// This is the representation used by money-like fields, e.g. 12345 with a
+/// scale of 2 is 123.45.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaDecimals<'a> {
+    values: &'a [i64],
+    scale: u8,
+}
+
+impl Arena {
+    /// Copies the scaled integers into this arena, to be read with `scale`.
+    /// Returns None if the allocation failed.
+    pub fn copy_decimals_in(&self, values: &[i64], scale: u8) -> Option<ArenaDecimals<'_>> {
+        Some(ArenaDecimals { values: self.copy_slice_in(values)?, scale })
+    }
+}
+
+impl ArenaDecimals<'_> {
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the number of decimal digits after the point.
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Returns the scaled integer at `index` and the scale.
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn get(&self, index: usize) -> (i64, u8) {
+        (self.values[index], self.scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_decimals_in() {
+        let arena = Arena::new();
+        let decimals = arena.copy_decimals_in(&[12345, 67800], 2).unwrap();
+        assert_eq!(decimals.len(), 2);
+        assert_eq!(decimals.scale(), 2);
+        assert_eq!(decimals.get(0), (12345, 2));
+        assert_eq!(decimals.get(1), (67800, 2));
+    }
+}
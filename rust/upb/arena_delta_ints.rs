@@ -0,0 +1,132 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::slice;
+
+/// A sequence of integers held in a upb Arena as its first value followed by
+/// the zigzag varint encoded difference of each value from the previous one.
+///
+/// Nearly sorted sequences like timestamps or ids take one or two bytes per
+/// value, but indexing decodes every delta up to the index.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaDeltaInts<'a> {
+    base: i64,
+    len: usize,
+    deltas: &'a [u8],
+}
+
+/// Returns the zigzag encoded difference of each value in `data` from the
+/// previous one.
+fn zigzag_deltas(data: &[i64]) -> impl Iterator<Item = u64> + '_ {
+    data.windows(2).map(|pair| {
+        let delta = pair[1].wrapping_sub(pair[0]);
+        ((delta << 1) ^ (delta >> 63)) as u64
+    })
+}
+
+/// Returns the number of bytes `value` takes as a varint.
+fn varint_len(value: u64) -> usize {
+    (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
+
+impl Arena {
+    /// Copies `data` into this arena delta encoded. Returns None if the
+    /// allocation failed.
+    ///
+    /// The encoded length is computed first, so the deltas are written
+    /// straight into a single allocation of exactly that size.
+    pub fn copy_delta_in(&self, data: &[i64]) -> Option<ArenaDeltaInts<'_>> {
+        let size = zigzag_deltas(data).try_fold(0usize, |sum, z| sum.checked_add(varint_len(z)))?;
+        let alloc = self.checked_alloc(size, 1)?;
+        let mut written = 0;
+        for mut zigzag in zigzag_deltas(data) {
+            while zigzag >= 0x80 {
+                alloc[written].write(zigzag as u8 | 0x80);
+                written += 1;
+                zigzag >>= 7;
+            }
+            alloc[written].write(zigzag as u8);
+            written += 1;
+        }
+        debug_assert_eq!(written, size);
+        Some(ArenaDeltaInts {
+            base: data.first().copied().unwrap_or(0),
+            len: data.len(),
+            // SAFETY: all `size` bytes of `alloc` were written above.
+            deltas: unsafe { slice::from_raw_parts(alloc.as_ptr().cast::<u8>(), size) },
+        })
+    }
+}
+
+impl ArenaDeltaInts<'_> {
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes the deltas take in the arena.
+    pub fn encoded_len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Returns the value at `index`.
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn get(&self, index: usize) -> i64 {
+        assert!(index < self.len, "index out of bounds");
+        let mut value = self.base;
+        let mut bytes = self.deltas.iter();
+        for _ in 0..index {
+            let mut zigzag = 0u64;
+            let mut shift = 0;
+            for &byte in bytes.by_ref() {
+                zigzag |= u64::from(byte & 0x7f) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+            value = value.wrapping_add(delta);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_delta_in() {
+        let arena = Arena::new();
+        let data = [100, 101, 105, 200];
+        let ints = arena.copy_delta_in(&data).unwrap();
+        assert_eq!(ints.len(), 4);
+        assert_eq!(ints.encoded_len(), 4);
+        for (i, &x) in data.iter().enumerate() {
+            assert_eq!(ints.get(i), x);
+        }
+    }
+
+    #[gtest]
+    fn test_copy_delta_in_extremes() {
+        let arena = Arena::new();
+        let data = [i64::MIN, i64::MAX, 0, -1, i64::MIN];
+        let ints = arena.copy_delta_in(&data).unwrap();
+        for (i, &x) in data.iter().enumerate() {
+            assert_eq!(ints.get(i), x);
+        }
+        assert!(arena.copy_delta_in(&[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::ops::Deref;
+
+/// Bytes held in a upb Arena, packaged so they can be moved into another
+/// task or thread that the borrow checker can prove does not outlive the
+/// arena.
+///
+/// Unlike `&Arena`, an `ArenaGuard` is `Send`: it only allows reading the data
+/// it was created with, not allocating on the arena.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaGuard<'a> {
+    data: &'a [u8],
+}
+
+impl Arena {
+    /// Wraps `data`, usually memory of this arena, in a guard which can not
+    /// outlive the arena. Nothing is copied.
+    pub fn borrow_for<'a>(&'a self, data: &'a [u8]) -> ArenaGuard<'a> {
+        ArenaGuard { data }
+    }
+
+    /// Copies the bytes into this arena and returns a guard over the copy.
+    /// Returns None if the allocation failed.
+    pub fn copy_in_for<'a>(&'a self, data: &[u8]) -> Option<ArenaGuard<'a>> {
+        self.copy_slice_in(data).map(|data| self.borrow_for(data))
+    }
+}
+
+impl<'a> ArenaGuard<'a> {
+    /// Returns the guarded data for the full lifetime of the arena.
+    pub fn get(self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl Deref for ArenaGuard<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_guard_moved_into_scoped_thread() {
+        let arena = Arena::new();
+        let data = arena.copy_slice_in(b"hello").unwrap();
+        let guard = arena.borrow_for(data);
+        assert!(core::ptr::eq(guard.get(), data));
+        let len = std::thread::scope(|s| {
+            s.spawn(move || {
+                assert_eq!(&*guard, b"hello");
+                guard.len()
+            })
+            .join()
+            .unwrap()
+        });
+        assert_eq!(len, 5);
+    }
+
+    #[gtest]
+    fn test_copy_in_for() {
+        let arena = Arena::new();
+        let data = b"hello".to_vec();
+        let guard = arena.copy_in_for(&data).unwrap();
+        drop(data);
+        assert_eq!(&*guard, b"hello");
+    }
+}
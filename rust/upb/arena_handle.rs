@@ -0,0 +1,115 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::fmt;
+use core::marker::PhantomData;
+use core::slice;
+
+/// An opaque handle to a slice copied into a upb Arena by
+/// `Arena::copy_slice_in_indexed`, which `Arena::resolve` turns back into
+/// the slice.
+///
+/// Unlike a reference, a handle does not borrow the arena, so it can be
+/// stored next to the `Arena` in the same struct. Moving the `Arena` does not
+/// move its memory, so handles stay valid until the arena is dropped.
+pub struct ArenaHandle<T> {
+    arena_id: u64,
+    addr: usize,
+    len: usize,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl Arena {
+    /// Copies `data` into this arena and returns a handle to the copy.
+    /// Returns None if the allocation failed.
+    pub fn copy_slice_in_indexed<T: Copy>(&self, data: &[T]) -> Option<ArenaHandle<T>> {
+        let copy = self.copy_slice_in(data)?;
+        // SAFETY: `copy` was just allocated on this arena.
+        Some(unsafe { self.handle_of(copy) })
+    }
+
+    /// Returns a handle to `data`, which is already in this arena.
+    ///
+    /// # Safety
+    /// - `data` must have been allocated on this arena.
+    pub(crate) unsafe fn handle_of<T: Copy>(&self, data: &[T]) -> ArenaHandle<T> {
+        ArenaHandle {
+            arena_id: self.handle_id(),
+            addr: data.as_ptr() as usize,
+            len: data.len(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the slice `handle` refers to.
+    ///
+    /// Panics if `handle` was returned by another arena.
+    pub fn resolve<T: Copy>(&self, handle: ArenaHandle<T>) -> &[T] {
+        assert_eq!(handle.arena_id, self.handle_id(), "handle is from a different arena");
+        // SAFETY: `handle` refers to `len` initialized values of `T` copied into this
+        // arena, which lives as long as `self`.
+        unsafe { slice::from_raw_parts(handle.addr as *const T, handle.len) }
+    }
+}
+
+impl<T> Clone for ArenaHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaHandle<T> {}
+
+impl<T> fmt::Debug for ArenaHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArenaHandle").field("addr", &self.addr).field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    struct Owner {
+        arena: Arena,
+        names: Vec<ArenaHandle<u8>>,
+        values: ArenaHandle<u32>,
+    }
+
+    #[gtest]
+    fn test_resolve_after_moving_arena() {
+        let arena = Arena::new();
+        let names = vec![
+            arena.copy_slice_in_indexed(b"alpha").unwrap(),
+            arena.copy_slice_in_indexed(b"beta").unwrap(),
+        ];
+        let values = arena.copy_slice_in_indexed(&[1u32, 2, 3]).unwrap();
+        let owner = Box::new(Owner { arena, names, values });
+
+        assert_eq!(owner.arena.resolve(owner.names[0]), b"alpha");
+        assert_eq!(owner.arena.resolve(owner.names[1]), b"beta");
+        assert_eq!(owner.arena.resolve(owner.values), &[1, 2, 3]);
+    }
+
+    #[gtest]
+    #[should_panic(expected = "handle is from a different arena")]
+    fn test_resolve_other_arena() {
+        let arena = Arena::new();
+        let handle = arena.copy_slice_in_indexed(&[1]).unwrap();
+        Arena::new().resolve(handle);
+    }
+
+    #[gtest]
+    #[should_panic(expected = "handle is from a different arena")]
+    fn test_resolve_after_arena_dropped() {
+        let handle = Arena::new().copy_slice_in_indexed(&[1]).unwrap();
+        // upb may hand out the freed arena again; the handle must still not resolve.
+        Arena::new().resolve(handle);
+    }
+}
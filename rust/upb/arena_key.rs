@@ -0,0 +1,57 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::borrow::Borrow;
+
+/// Bytes in a upb Arena which can be used as a map key.
+///
+/// Keys compare and hash by the bytes they point to, not by address, so two
+/// copies of the same bytes are the same key. It also implements
+/// `Borrow<[u8]>`, so a map can be queried with a plain `&[u8]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ArenaKey<'a>(&'a [u8]);
+
+impl Arena {
+    /// Copies the bytes into this arena as a map key. Returns None if the
+    /// allocation failed.
+    pub fn copy_key_in<'a>(&'a self, bytes: &[u8]) -> Option<ArenaKey<'a>> {
+        self.copy_slice_in(bytes).map(ArenaKey)
+    }
+}
+
+impl<'a> ArenaKey<'a> {
+    /// Returns the bytes of the key.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+impl Borrow<[u8]> for ArenaKey<'_> {
+    fn borrow(&self) -> &[u8] {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+    use std::collections::HashMap;
+
+    #[gtest]
+    fn test_keys_compare_by_content() {
+        let arena = Arena::new();
+        let mut counts = HashMap::new();
+        for bytes in [&b"alpha"[..], b"beta", b"alpha"] {
+            *counts.entry(arena.copy_key_in(bytes).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts.get(&b"alpha"[..]), Some(&2));
+        assert_eq!(counts.get(&b"beta"[..]), Some(&1));
+    }
+}
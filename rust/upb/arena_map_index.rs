@@ -0,0 +1,83 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::mem::{align_of, size_of};
+use core::slice;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Values held in a upb Arena, indexed by key through a heap-allocated hash
+/// map.
+#[derive(Debug, Clone)]
+pub struct ArenaMapIndex<'a, K, V> {
+    values: &'a [V],
+    index: HashMap<K, usize>,
+}
+
+impl Arena {
+    /// Copies the values of `entries` into this arena and indexes them by key.
+    /// Returns None if the allocation failed.
+    ///
+    /// If a key appears more than once, lookups return its last value.
+    pub fn copy_map_index_in<'a, K: Copy + Eq + Hash, V: Copy>(
+        &'a self,
+        entries: &[(K, V)],
+    ) -> Option<ArenaMapIndex<'a, K, V>> {
+        let size = size_of::<V>().checked_mul(entries.len())?;
+        let values = self.checked_alloc(size, align_of::<V>())?.as_mut_ptr().cast::<V>();
+        for (i, &(_, v)) in entries.iter().enumerate() {
+            // SAFETY: `values` is valid for `entries.len()` values and aligned for them.
+            unsafe { values.add(i).write(v) };
+        }
+        // SAFETY: every value was written by the loop above.
+        let values = unsafe { slice::from_raw_parts(values, entries.len()) };
+        let index = entries.iter().enumerate().map(|(i, &(k, _))| (k, i)).collect();
+        Some(ArenaMapIndex { values, index })
+    }
+}
+
+impl<'a, K: Eq + Hash, V> ArenaMapIndex<'a, K, V> {
+    /// Returns the arena-held value for `k`, if any.
+    pub fn get(&self, k: &K) -> Option<&'a V> {
+        self.index.get(k).map(|&i| &self.values[i])
+    }
+
+    /// Returns the number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_map_index_in() {
+        let arena = Arena::new();
+        let index = arena.copy_map_index_in(&[(1u32, 'a'), (7, 'b'), (3, 'c')]).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get(&1), Some(&'a'));
+        assert_eq!(index.get(&7), Some(&'b'));
+        assert_eq!(index.get(&3), Some(&'c'));
+        assert_eq!(index.get(&2), None);
+    }
+
+    #[gtest]
+    fn test_copy_map_index_in_duplicate_key() {
+        let arena = Arena::new();
+        let index = arena.copy_map_index_in(&[("k", 1), ("k", 2)]).unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.get(&"k"), Some(&2));
+    }
+}
@@ -0,0 +1,90 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+
+/// A row-major 2D view over data held in a upb Arena.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaMatrix<'a, T> {
+    data: &'a [T],
+    rows: usize,
+    cols: usize,
+}
+
+impl Arena {
+    /// Copies the row-major `data` into this arena as a `rows` by `cols`
+    /// matrix. Returns None if the allocation failed.
+    ///
+    /// Panics if `rows * cols != data.len()`.
+    pub fn copy_matrix_in<'a, T: Copy>(
+        &'a self,
+        rows: usize,
+        cols: usize,
+        data: &[T],
+    ) -> Option<ArenaMatrix<'a, T>> {
+        assert_eq!(rows.checked_mul(cols), Some(data.len()), "matrix dimensions mismatch");
+        let data = self.copy_slice_in(data)?;
+        Some(ArenaMatrix { data, rows, cols })
+    }
+}
+
+impl<'a, T> ArenaMatrix<'a, T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the element at row `r` and column `c`.
+    ///
+    /// Panics if `r >= self.rows()` or `c >= self.cols()`.
+    pub fn get(&self, r: usize, c: usize) -> &'a T {
+        assert!(r < self.rows && c < self.cols, "matrix index out of bounds");
+        &self.data[r * self.cols + c]
+    }
+
+    /// Returns the underlying row-major data.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_matrix_in() {
+        let arena = Arena::new();
+        let data: Vec<u32> = (0..12).collect();
+        let matrix = arena.copy_matrix_in(3, 4, &data).unwrap();
+        assert_eq!(matrix.rows(), 3);
+        assert_eq!(matrix.cols(), 4);
+        assert_eq!(*matrix.get(0, 0), 0);
+        assert_eq!(*matrix.get(1, 2), 6);
+        assert_eq!(*matrix.get(2, 3), 11);
+        assert_eq!(matrix.as_slice(), &data[..]);
+    }
+
+    #[gtest]
+    fn test_copy_empty_matrix_in() {
+        let arena = Arena::new();
+        let matrix = arena.copy_matrix_in::<u32>(0, 4, &[]).unwrap();
+        assert_eq!(matrix.rows(), 0);
+        assert!(matrix.as_slice().is_empty());
+    }
+
+    #[gtest]
+    #[should_panic(expected = "matrix dimensions mismatch")]
+    fn test_copy_matrix_in_mismatched_dimensions() {
+        let arena = Arena::new();
+        arena.copy_matrix_in(2, 2, &[1, 2, 3]);
+    }
+}
@@ -0,0 +1,94 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::mem::{align_of, size_of};
+use core::ops::Deref;
+use core::ptr::NonNull;
+
+/// A pointer to a `T` held in a upb Arena, bundled with the layout metadata
+/// that FFI consumers need to pass it across module boundaries.
+pub struct ArenaPtr<'a, T> {
+    value: &'a T,
+}
+
+impl Arena {
+    /// Copies the T into this arena and returns a described pointer to it.
+    /// Returns None if the allocation failed.
+    pub fn copy_in_described<'a, T: Copy>(&'a self, value: &T) -> Option<ArenaPtr<'a, T>> {
+        self.copy_in(value).map(|value| ArenaPtr { value })
+    }
+}
+
+impl<'a, T> ArenaPtr<'a, T> {
+    pub fn as_ptr(&self) -> NonNull<T> {
+        NonNull::from(self.value)
+    }
+
+    /// Size in bytes of the pointed-to `T`.
+    pub fn size(&self) -> usize {
+        size_of::<T>()
+    }
+
+    /// Alignment in bytes of the pointed-to `T`.
+    pub fn align(&self) -> usize {
+        align_of::<T>()
+    }
+
+    /// Returns the reference for the full lifetime of the arena.
+    pub fn get(&self) -> &'a T {
+        self.value
+    }
+}
+
+impl<T> Clone for ArenaPtr<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaPtr<'_, T> {}
+
+impl<T> Deref for ArenaPtr<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for ArenaPtr<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ArenaPtr")
+            .field("ptr", &self.as_ptr())
+            .field("size", &self.size())
+            .field("align", &self.align())
+            .field("value", self.value)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_in_described() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Point {
+            x: u32,
+            y: u16,
+        }
+
+        let arena = Arena::new();
+        let ptr = arena.copy_in_described(&Point { x: 1, y: 2 }).unwrap();
+        assert_eq!(ptr.size(), size_of::<Point>());
+        assert_eq!(ptr.align(), align_of::<Point>());
+        assert_eq!(*ptr, Point { x: 1, y: 2 });
+        assert_eq!(ptr.as_ptr().as_ptr() as usize % ptr.align(), 0);
+    }
+}
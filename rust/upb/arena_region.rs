@@ -0,0 +1,87 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+
+/// Size of each little-endian integer in a serialized region.
+const WORD: usize = core::mem::size_of::<u64>();
+
+impl Arena {
+    /// Flattens `roots` into a self-contained buffer which holds no pointers,
+    /// so it can be written to a file and mapped back in later.
+    ///
+    /// The buffer starts with the number of slices, followed by the offset and
+    /// length of each slice relative to the end of this header, and then the
+    /// bytes of all of the slices. All integers are little-endian `u64`s.
+    pub fn serialize_region(&self, roots: &[&[u8]]) -> Vec<u8> {
+        let mut header = Vec::with_capacity(WORD * (1 + 2 * roots.len()));
+        header.extend_from_slice(&(roots.len() as u64).to_le_bytes());
+        let mut offset = 0;
+        for root in roots {
+            header.extend_from_slice(&(offset as u64).to_le_bytes());
+            header.extend_from_slice(&(root.len() as u64).to_le_bytes());
+            offset += root.len();
+        }
+        let mut buf = header;
+        buf.reserve(offset);
+        for root in roots {
+            buf.extend_from_slice(root);
+        }
+        buf
+    }
+
+    /// Copies the slices of a buffer made by `serialize_region` into `arena`.
+    /// Returns None if `buf` is malformed or if the allocation failed.
+    ///
+    /// The data is copied in with a single allocation, which the returned
+    /// slices point into.
+    pub fn deserialize_region<'a>(arena: &'a Arena, buf: &[u8]) -> Option<Vec<&'a [u8]>> {
+        let mut words = buf.chunks_exact(WORD).map(|w| {
+            usize::try_from(u64::from_le_bytes(w.try_into().unwrap())).unwrap_or(usize::MAX)
+        });
+        let count = words.next()?;
+        let header_len = count.checked_mul(2)?.checked_add(1)?.checked_mul(WORD)?;
+        let data = arena.copy_slice_in(buf.get(header_len..)?)?;
+        (0..count)
+            .map(|_| {
+                let (offset, len) = (words.next()?, words.next()?);
+                data.get(offset..offset.checked_add(len)?)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_region_round_trip() {
+        let arena = Arena::new();
+        let roots = [
+            arena.copy_slice_in(b"first").unwrap(),
+            arena.copy_slice_in(b"").unwrap(),
+            arena.copy_slice_in(b"third slice").unwrap(),
+        ];
+        let buf = arena.serialize_region(&roots);
+        assert_eq!(buf.len(), WORD * 7 + 16);
+
+        let other = Arena::new();
+        let slices = Arena::deserialize_region(&other, &buf).unwrap();
+        assert_eq!(slices, roots);
+    }
+
+    #[gtest]
+    fn test_deserialize_region_malformed() {
+        let arena = Arena::new();
+        let buf = arena.serialize_region(&[b"abc"]);
+        assert_eq!(Arena::deserialize_region(&arena, &buf[..buf.len() - 1]), None);
+        assert_eq!(Arena::deserialize_region(&arena, &buf[..WORD + 4]), None);
+        assert_eq!(Arena::deserialize_region(&arena, &[]), None);
+    }
+}
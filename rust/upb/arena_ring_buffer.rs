@@ -0,0 +1,107 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::mem::{align_of, size_of, MaybeUninit};
+use core::slice;
+
+/// A fixed-capacity window over the most recent values pushed to it, stored
+/// in a single arena allocation.
+///
+/// Once full, each push overwrites the oldest value, so a stream of any
+/// length can be windowed without allocating per push.
+#[derive(Debug)]
+pub struct ArenaRingBuffer<'a, T> {
+    data: &'a mut [MaybeUninit<T>],
+    // Index of the oldest value, and the number of values held.
+    start: usize,
+    len: usize,
+}
+
+impl Arena {
+    /// Returns an empty ring buffer holding up to `capacity` values. Returns
+    /// None if the allocation failed.
+    pub fn ring_buffer<T: Copy>(&self, capacity: usize) -> Option<ArenaRingBuffer<'_, T>> {
+        let alloc = self.alloc_aligned(size_of::<T>().checked_mul(capacity)?, align_of::<T>())?;
+        // SAFETY: `alloc` is valid for `capacity` elements of `T` and aligned for
+        // `T`, and `MaybeUninit<T>` has no validity requirements.
+        let data = unsafe { slice::from_raw_parts_mut(alloc.as_mut_ptr().cast(), capacity) };
+        Some(ArenaRingBuffer { data, start: 0, len: 0 })
+    }
+}
+
+impl<'a, T: Copy> ArenaRingBuffer<'a, T> {
+    /// Appends `value`, overwriting the oldest value if the buffer is full.
+    /// With a capacity of 0, the value is discarded.
+    pub fn push(&mut self, value: T) {
+        let capacity = self.data.len();
+        if capacity == 0 {
+            return;
+        }
+        if self.len < capacity {
+            self.data[(self.start + self.len) % capacity].write(value);
+            self.len += 1;
+        } else {
+            self.data[self.start].write(value);
+            self.start = (self.start + 1) % capacity;
+        }
+    }
+
+    /// Returns the values held, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let capacity = self.data.len();
+        (0..self.len).map(move |i| {
+            // SAFETY: the `len` slots from `start` onwards, wrapping around, have
+            // been written by `push`.
+            unsafe { self.data[(self.start + i) % capacity].assume_init_ref() }
+        })
+    }
+
+    /// Returns the number of values held.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no values have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the most values the buffer holds at once.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_holds_last_n_values() {
+        let arena = Arena::new();
+        let mut window = arena.ring_buffer::<u32>(3).unwrap();
+        window.push(1);
+        window.push(2);
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), [1, 2]);
+        for i in 3..=7 {
+            window.push(i);
+        }
+        assert_eq!(window.len(), 3);
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), [5, 6, 7]);
+    }
+
+    #[gtest]
+    fn test_zero_capacity() {
+        let arena = Arena::new();
+        let mut window = arena.ring_buffer::<u64>(0).unwrap();
+        window.push(1);
+        assert!(window.is_empty());
+        assert_eq!(window.iter().count(), 0);
+    }
+}
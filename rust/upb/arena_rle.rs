@@ -0,0 +1,92 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+
+/// A run-length encoded slice held in a upb Arena.
+///
+/// Each run is stored as its value and the index one past its last element,
+/// so indexing is a binary search over the runs.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaRle<'a, T> {
+    runs: &'a [(T, usize)],
+}
+
+impl Arena {
+    /// Copies `data` into this arena as runs of equal values. Returns None if
+    /// the allocation failed.
+    ///
+    /// This trades slower indexing for less memory on highly repetitive data.
+    pub fn copy_rle_in<'a, T: Copy + PartialEq>(&'a self, data: &[T]) -> Option<ArenaRle<'a, T>> {
+        let mut runs: Vec<(T, usize)> = Vec::new();
+        for (i, &value) in data.iter().enumerate() {
+            match runs.last_mut() {
+                Some((last, end)) if *last == value => *end = i + 1,
+                _ => runs.push((value, i + 1)),
+            }
+        }
+        Some(ArenaRle { runs: self.copy_slice_in(&runs)? })
+    }
+}
+
+impl<'a, T: Copy> ArenaRle<'a, T> {
+    /// Returns the number of elements in the decoded slice.
+    pub fn len(&self) -> usize {
+        self.runs.last().map_or(0, |&(_, end)| end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Returns the number of runs stored.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Returns the element at `index` of the decoded slice.
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn get(&self, index: usize) -> T {
+        let run = self.runs.partition_point(|&(_, end)| end <= index);
+        assert!(run < self.runs.len(), "index out of bounds");
+        self.runs[run].0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_rle_in() {
+        let arena = Arena::new();
+        let data = [1, 1, 1, 2, 3, 3];
+        let rle = arena.copy_rle_in(&data).unwrap();
+        assert_eq!(rle.len(), 6);
+        assert_eq!(rle.run_count(), 3);
+        for (i, &x) in data.iter().enumerate() {
+            assert_eq!(rle.get(i), x);
+        }
+    }
+
+    #[gtest]
+    fn test_copy_empty_rle_in() {
+        let arena = Arena::new();
+        let rle = arena.copy_rle_in::<u8>(&[]).unwrap();
+        assert!(rle.is_empty());
+        assert_eq!(rle.len(), 0);
+    }
+
+    #[gtest]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_rle_get_out_of_bounds() {
+        let arena = Arena::new();
+        arena.copy_rle_in(&[1, 1]).unwrap().get(2);
+    }
+}
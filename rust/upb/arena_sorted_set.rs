@@ -0,0 +1,102 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::mem::{size_of, size_of_val};
+use core::ptr::NonNull;
+
+/// A sorted, deduplicated set held in a upb Arena, with lookups by binary
+/// search.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaSortedSet<'a, T> {
+    data: &'a [T],
+}
+
+impl Arena {
+    /// Copies `data` into this arena as a sorted set, dropping duplicate
+    /// values. Returns None if the allocation failed.
+    ///
+    /// The sort and deduplication happen in place in the arena copy, so no
+    /// other memory is allocated.
+    pub fn copy_sorted_set_in<'a, T: Copy + Ord>(
+        &'a self,
+        data: &[T],
+    ) -> Option<ArenaSortedSet<'a, T>> {
+        let copy = self.copy_slice_in_mut(data)?;
+        copy.sort_unstable();
+        let mut len = 0;
+        for i in 0..copy.len() {
+            if len == 0 || copy[len - 1] != copy[i] {
+                copy[len] = copy[i];
+                len += 1;
+            }
+        }
+        if len < copy.len() {
+            let oldsize = size_of_val(copy);
+            // SAFETY: `copy` is an allocation of `oldsize` bytes from this arena, and
+            // only the first `len` elements are accessed after this.
+            unsafe {
+                self.shrink_last(NonNull::from(&mut *copy).cast(), oldsize, size_of::<T>() * len)
+            };
+        }
+        Some(ArenaSortedSet { data: &copy[..len] })
+    }
+}
+
+impl<'a, T: Ord> ArenaSortedSet<'a, T> {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.position(value).is_some()
+    }
+
+    /// Returns the index of `value` in the sorted set, if it is present.
+    pub fn position(&self, value: &T) -> Option<usize> {
+        self.data.binary_search(value).ok()
+    }
+
+    /// Returns the values in ascending order.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_sorted_set_in() {
+        let arena = Arena::new();
+        let set = arena.copy_sorted_set_in(&[30, 10, 20, 10, 30]).unwrap();
+        assert_eq!(set.as_slice(), &[10, 20, 30]);
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&20));
+        assert_eq!(set.position(&10), Some(0));
+        assert_eq!(set.position(&30), Some(2));
+    }
+
+    #[gtest]
+    fn test_sorted_set_absent() {
+        let arena = Arena::new();
+        let set = arena.copy_sorted_set_in(&[5u32, 1, 3]).unwrap();
+        assert!(!set.contains(&0));
+        assert!(!set.contains(&2));
+        assert_eq!(set.position(&6), None);
+
+        let empty = arena.copy_sorted_set_in::<u32>(&[]).unwrap();
+        assert!(empty.is_empty());
+        assert!(!empty.contains(&1));
+    }
+}
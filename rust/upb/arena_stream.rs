@@ -0,0 +1,110 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::fmt;
+use core::ptr::{self, NonNull};
+use core::slice;
+use futures::{Stream, StreamExt};
+
+/// An error returned by `Arena::copy_from_stream_in` when the stream did not
+/// hold exactly the expected number of bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamLengthMismatch {
+    /// The number of bytes the stream was expected to hold.
+    pub expected: usize,
+    /// The number of bytes received before the copy stopped: fewer than
+    /// `expected` if the stream ended early, more if a chunk overran it.
+    pub received: usize,
+}
+
+impl std::error::Error for StreamLengthMismatch {}
+
+impl fmt::Display for StreamLengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {} bytes from the stream, received {}", self.expected, self.received)
+    }
+}
+
+impl Arena {
+    /// Allocates `total_len` bytes and fills them with the chunks of
+    /// `stream` as they arrive. Returns `Ok(None)` if the allocation failed.
+    ///
+    /// The stream is polled until `total_len` bytes have been received and
+    /// no further, so it can carry more data after them. If it ends early or
+    /// a chunk runs past `total_len`, an error is returned and the allocation
+    /// is given back to the arena if it is still the most recent one.
+    pub async fn copy_from_stream_in<'a, S: Stream<Item = Vec<u8>> + Unpin>(
+        &'a self,
+        stream: &mut S,
+        total_len: usize,
+    ) -> Result<Option<&'a [u8]>, StreamLengthMismatch> {
+        let Some(alloc) = self.checked_alloc(total_len, 1) else {
+            return Ok(None);
+        };
+        let alloc = NonNull::from(alloc).cast::<u8>();
+        let mut received = 0;
+        while received < total_len {
+            let chunk = stream.next().await;
+            let end = received + chunk.as_ref().map_or(0, Vec::len);
+            match chunk {
+                Some(chunk) if end <= total_len => {
+                    // SAFETY: `alloc` is valid for `total_len` bytes, so for the
+                    // `chunk.len()` bytes after the `received` so far.
+                    unsafe {
+                        ptr::copy_nonoverlapping(
+                            chunk.as_ptr(),
+                            alloc.as_ptr().add(received),
+                            chunk.len(),
+                        )
+                    };
+                    received = end;
+                }
+                _ => {
+                    // SAFETY: `alloc` is an allocation of `total_len` bytes from this
+                    // arena and nothing refers to it.
+                    unsafe { self.shrink_last(alloc, total_len, 0) };
+                    return Err(StreamLengthMismatch { expected: total_len, received: end });
+                }
+            }
+        }
+        // SAFETY: all `total_len` bytes of `alloc` were written by the loop above.
+        Ok(Some(unsafe { slice::from_raw_parts(alloc.as_ptr(), total_len) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::stream;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_from_stream_in() {
+        let arena = Arena::new();
+        let chunks = vec![b"hello".to_vec(), Vec::new(), b", ".to_vec(), b"world".to_vec()];
+        let mut chunks = stream::iter(chunks);
+        let copy = block_on(arena.copy_from_stream_in(&mut chunks, 12)).unwrap().unwrap();
+        assert_eq!(copy, b"hello, world");
+    }
+
+    #[gtest]
+    fn test_copy_from_stream_in_length_mismatch() {
+        let arena = Arena::new();
+        let mut short = stream::iter(vec![b"abc".to_vec()]);
+        assert_eq!(
+            block_on(arena.copy_from_stream_in(&mut short, 5)),
+            Err(StreamLengthMismatch { expected: 5, received: 3 })
+        );
+        let mut long = stream::iter(vec![b"abc".to_vec(), b"def".to_vec()]);
+        assert_eq!(
+            block_on(arena.copy_from_stream_in(&mut long, 5)),
+            Err(StreamLengthMismatch { expected: 5, received: 6 })
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::{AppendableSlice, Arena};
+use core::fmt;
+
+/// A string built up in a upb Arena, e.g. with `write!`.
+///
+/// The bytes are kept in an `AppendableSlice`, so fragments written while
+/// nothing else is allocated from the arena extend the string in place.
+#[derive(Debug)]
+pub struct ArenaString<'a> {
+    bytes: AppendableSlice<'a, u8>,
+}
+
+impl Arena {
+    /// Returns an empty string which can be written to with `fmt::Write`.
+    pub fn arena_string(&self) -> ArenaString<'_> {
+        ArenaString { bytes: self.appendable_slice() }
+    }
+}
+
+impl<'a> ArenaString<'a> {
+    /// Returns the string written so far.
+    pub fn as_str(&self) -> &'a str {
+        // SAFETY: only whole `str`s are appended by `write_str`.
+        unsafe { core::str::from_utf8_unchecked(self.bytes.as_slice()) }
+    }
+}
+
+impl fmt::Write for ArenaString<'_> {
+    /// Appends `s`, failing if the allocation failed.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.bytes.push_all(s.as_bytes()).map(|_| ()).ok_or(fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_write_fragments() {
+        let arena = Arena::new();
+        let mut s = arena.arena_string();
+        assert_eq!(s.as_str(), "");
+        write!(s, "field {}", 1).unwrap();
+        write!(s, ": {:?}", "value").unwrap();
+        s.write_char('!').unwrap();
+        assert_eq!(s.as_str(), "field 1: \"value\"!");
+    }
+}
@@ -0,0 +1,77 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+
+/// A pool of strings in a upb Arena which stores each string only if it is
+/// not already a substring of one stored before.
+///
+/// Lookups scan all of the stored strings, so this suits pools of modest size
+/// where saving arena space matters more than the cost of storing.
+#[derive(Debug)]
+pub struct ArenaSuffixPool<'a> {
+    arena: &'a Arena,
+    stored: Vec<&'a str>,
+}
+
+/// A string stored in an `ArenaSuffixPool`, which may overlap other spans
+/// from the same pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArenaSpan<'a> {
+    text: &'a str,
+}
+
+impl Arena {
+    /// Returns an empty pool which stores its strings in this arena.
+    pub fn suffix_pool(&self) -> ArenaSuffixPool<'_> {
+        ArenaSuffixPool { arena: self, stored: Vec::new() }
+    }
+}
+
+impl<'a> ArenaSuffixPool<'a> {
+    /// Returns a span of the stored data equal to `s`, copying `s` into the
+    /// arena only if no stored string contains it. Returns None if the
+    /// allocation failed.
+    pub fn store(&mut self, s: &str) -> Option<ArenaSpan<'a>> {
+        for stored in &self.stored {
+            if let Some(start) = stored.find(s) {
+                return Some(ArenaSpan { text: &stored[start..start + s.len()] });
+            }
+        }
+        let text = self.arena.copy_str_in(s)?;
+        self.stored.push(text);
+        Some(ArenaSpan { text })
+    }
+}
+
+impl<'a> ArenaSpan<'a> {
+    /// Returns the text of the span.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_store_substring_reuses_storage() {
+        let arena = Arena::new();
+        let mut pool = arena.suffix_pool();
+        let first = pool.store("hello world").unwrap();
+        let second = pool.store("world").unwrap();
+        assert_eq!(second.as_str(), "world");
+        assert_eq!(second.as_str().as_ptr(), first.as_str()[6..].as_ptr());
+
+        let third = pool.store("goodbye").unwrap();
+        assert_eq!(third.as_str(), "goodbye");
+        assert_ne!(third.as_str().as_ptr(), first.as_str().as_ptr());
+        assert_eq!(pool.store("bye").unwrap().as_str().as_ptr(), third.as_str()[4..].as_ptr());
+    }
+}
@@ -0,0 +1,109 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::mem::{align_of, size_of};
+use core::{ptr, slice};
+
+/// Variable-length byte records held contiguously in a upb Arena, with an
+/// arena-held offset table for indexed access.
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaVarRecords<'a> {
+    bytes: &'a [u8],
+    // `offsets[i]..offsets[i + 1]` is the range of record `i` in `bytes`.
+    offsets: &'a [usize],
+}
+
+impl Arena {
+    /// Copies the records into this arena back to back, along with a table of
+    /// their offsets. Returns None if an allocation failed.
+    pub fn copy_records_in<'a>(&'a self, records: &[&[u8]]) -> Option<ArenaVarRecords<'a>> {
+        let total = records.iter().try_fold(0usize, |sum, r| sum.checked_add(r.len()))?;
+        let bytes = self.checked_alloc(total, 1)?.as_mut_ptr().cast::<u8>();
+        let size = size_of::<usize>().checked_mul(records.len() + 1)?;
+        let offsets = self.checked_alloc(size, align_of::<usize>())?;
+        let offsets = offsets.as_mut_ptr().cast::<usize>();
+        let mut offset = 0;
+        // SAFETY: `offsets` is valid for `records.len() + 1` offsets and aligned for
+        // them.
+        unsafe { offsets.write(0) };
+        for (i, record) in records.iter().enumerate() {
+            // SAFETY:
+            // - `bytes` is valid for `total` bytes, the sum of the record lengths, so for
+            //   `record.len()` bytes after the preceding records.
+            // - `offsets` is valid for `records.len() + 1` offsets.
+            unsafe {
+                ptr::copy_nonoverlapping(record.as_ptr(), bytes.add(offset), record.len());
+                offset += record.len();
+                offsets.add(i + 1).write(offset);
+            }
+        }
+        // SAFETY: all `total` bytes and every offset were written above.
+        unsafe {
+            Some(ArenaVarRecords {
+                bytes: slice::from_raw_parts(bytes, total),
+                offsets: slice::from_raw_parts(offsets, records.len() + 1),
+            })
+        }
+    }
+}
+
+impl<'a> ArenaVarRecords<'a> {
+    /// Returns the number of records.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns record `i`.
+    ///
+    /// Panics if `i >= self.len()`.
+    pub fn record(&self, i: usize) -> &'a [u8] {
+        assert!(i < self.len(), "record index out of bounds");
+        &self.bytes[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let records = *self;
+        (0..self.len()).map(move |i| records.record(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_records_in() {
+        let arena = Arena::new();
+        let input: [&[u8]; 4] = [b"a", b"bcd", b"", b"ef"];
+        let records = arena.copy_records_in(&input).unwrap();
+        assert_eq!(records.len(), 4);
+        for (i, &expected) in input.iter().enumerate() {
+            assert_eq!(records.record(i), expected);
+        }
+        assert_eq!(records.iter().collect::<Vec<_>>(), input);
+    }
+
+    #[gtest]
+    fn test_copy_no_records_in() {
+        let arena = Arena::new();
+        let records = arena.copy_records_in(&[]).unwrap();
+        assert!(records.is_empty());
+    }
+
+    #[gtest]
+    #[should_panic(expected = "record index out of bounds")]
+    fn test_record_out_of_bounds() {
+        let arena = Arena::new();
+        arena.copy_records_in(&[b"x"]).unwrap().record(1);
+    }
+}
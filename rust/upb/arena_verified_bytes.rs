@@ -0,0 +1,78 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::arena::crc32;
+use super::Arena;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::slice;
+
+/// Bytes held in a upb Arena together with their CRC-32 (IEEE), so that
+/// in-memory corruption can be detected with `verify` before the bytes are
+/// used.
+///
+/// This is meant for long-lived arenas whose memory may be scribbled on by
+/// buggy FFI code; it is not a defense against deliberate tampering.
+#[derive(Debug)]
+pub struct ArenaVerifiedBytes<'a> {
+    // A pointer rather than a `&'a [u8]`, since the point is to notice writes
+    // which do not go through Rust references.
+    data: NonNull<u8>,
+    len: usize,
+    crc: &'a u32,
+    _phantom: PhantomData<&'a [u8]>,
+}
+
+impl Arena {
+    /// Copies `data` and its checksum into this arena. Returns None if an
+    /// allocation failed.
+    pub fn copy_verified_in(&self, data: &[u8]) -> Option<ArenaVerifiedBytes<'_>> {
+        let crc = self.copy_in(&crc32(data))?;
+        let copy = self.copy_slice_in_mut(data)?;
+        Some(ArenaVerifiedBytes {
+            data: NonNull::from(&mut *copy).cast(),
+            len: copy.len(),
+            crc,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl ArenaVerifiedBytes<'_> {
+    /// Returns the bytes, without verifying them.
+    pub fn bytes(&self) -> &[u8] {
+        // SAFETY: `data` is valid for `len` initialized bytes for as long as the
+        // arena lives.
+        unsafe { slice::from_raw_parts(self.data.as_ptr(), self.len) }
+    }
+
+    /// Recomputes the checksum of the bytes and returns whether it matches
+    /// the stored one.
+    pub fn verify(&self) -> bool {
+        crc32(self.bytes()) == *self.crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_verify_detects_corruption() {
+        let arena = Arena::new();
+        let verified = arena.copy_verified_in(b"payload").unwrap();
+        assert_eq!(verified.bytes(), b"payload");
+        assert!(verified.verify());
+
+        // SAFETY: `data` is valid for writes of `len` bytes and no reference to the
+        // bytes is live; this stands in for a buggy FFI writer.
+        unsafe { *verified.data.as_ptr().add(2) ^= 0x01 };
+        assert!(!verified.verify());
+        assert_eq!(verified.bytes(), b"paxload");
+    }
+}
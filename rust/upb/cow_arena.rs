@@ -0,0 +1,94 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A copy-on-write view over regions of a parent's data, keyed by `K`.
+///
+/// Reads fall through to the parent until a region is first mutated with
+/// `get_mut`, which copies it into the child arena; from then on, the child's
+/// copy is read and written instead. The parent's data is never modified, so
+/// many views can share one parent.
+#[derive(Debug)]
+pub struct CowArena<'p, 'c, K> {
+    parent: &'p HashMap<K, &'p [u8]>,
+    child: &'c Arena,
+    copies: HashMap<K, &'c mut [u8]>,
+}
+
+impl Arena {
+    /// Returns a copy-on-write view over `parent` which copies regions into
+    /// this arena when they are mutated.
+    pub fn cow_over<'p, K: Eq + Hash>(
+        &self,
+        parent: &'p HashMap<K, &'p [u8]>,
+    ) -> CowArena<'p, '_, K> {
+        CowArena { parent, child: self, copies: HashMap::new() }
+    }
+}
+
+impl<'p, 'c, K: Eq + Hash + Copy> CowArena<'p, 'c, K> {
+    /// Returns the region for `key`: the child's copy if it has been
+    /// mutated, otherwise the parent's data. Returns None if the parent has no
+    /// such region.
+    pub fn get(&self, key: K) -> Option<&[u8]> {
+        match self.copies.get(&key) {
+            Some(copy) => Some(copy),
+            None => self.parent.get(&key).copied(),
+        }
+    }
+
+    /// Returns the child's own copy of the region for `key`, copying it from
+    /// the parent on first use. Returns None if the parent has no such region
+    /// or if the allocation failed.
+    pub fn get_mut(&mut self, key: K) -> Option<&mut [u8]> {
+        match self.copies.entry(key) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let copy = self.child.copy_slice_in_mut(self.parent.get(&key)?)?;
+                Some(entry.insert(copy))
+            }
+        }
+    }
+
+    /// Returns whether the region for `key` has been copied into the child.
+    pub fn is_copied(&self, key: K) -> bool {
+        self.copies.contains_key(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_on_first_mutation() {
+        let parent_arena = Arena::new();
+        let parent: HashMap<u32, &[u8]> = HashMap::from([
+            (1, parent_arena.copy_slice_in(b"name").unwrap()),
+            (2, parent_arena.copy_slice_in(b"value").unwrap()),
+        ]);
+
+        let child = Arena::new();
+        let mut cow = child.cow_over(&parent);
+        assert_eq!(cow.get(1).unwrap().as_ptr(), parent[&1].as_ptr());
+        assert!(!cow.is_copied(1));
+
+        cow.get_mut(1).unwrap()[0] = b'g';
+        assert!(cow.is_copied(1));
+        assert_eq!(cow.get(1).unwrap(), b"game");
+        assert_ne!(cow.get(1).unwrap().as_ptr(), parent[&1].as_ptr());
+        assert_eq!(parent[&1], b"name");
+
+        assert_eq!(cow.get(2).unwrap().as_ptr(), parent[&2].as_ptr());
+        assert!(cow.get_mut(3).is_none());
+    }
+}
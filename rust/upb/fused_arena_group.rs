@@ -0,0 +1,104 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::{Arena, RawArena};
+use core::cell::RefCell;
+
+/// A set of arenas fused together, which keeps the memory of all of them
+/// alive for as long as the group lives.
+///
+/// Arenas join the group with `join`; since fusing is permanent, memory
+/// allocated by a member stays valid for the group's lifetime even after the
+/// member `Arena` itself is dropped. `Arena::copy_slice_in_fused` reflects
+/// this in the returned lifetime.
+#[derive(Debug)]
+pub struct FusedArenaGroup {
+    anchor: Arena,
+    // Raw pointers stay unique while the group lives, because a fused arena is
+    // not freed until every arena fused with it is.
+    members: RefCell<Vec<RawArena>>,
+}
+
+impl FusedArenaGroup {
+    pub fn new() -> Self {
+        let anchor = Arena::new();
+        let members = RefCell::new(vec![anchor.raw()]);
+        FusedArenaGroup { anchor, members }
+    }
+
+    /// Fuses `arena` into the group.
+    ///
+    /// Panics if the arenas could not be fused, see `Arena::fuse`.
+    pub fn join(&self, arena: &Arena) {
+        self.anchor.fuse(arena);
+        self.members.borrow_mut().push(arena.raw());
+    }
+
+    /// Returns whether `arena` has joined this group.
+    pub fn contains(&self, arena: &Arena) -> bool {
+        self.members.borrow().contains(&arena.raw())
+    }
+
+    /// Returns the arena owned by the group itself.
+    pub fn arena(&self) -> &Arena {
+        &self.anchor
+    }
+}
+
+impl Default for FusedArenaGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Arena {
+    /// Same as copy_slice_in() but returns a slice that lives as long as
+    /// `group` rather than `self`. Returns None if the allocation failed.
+    ///
+    /// Panics if `self` has not joined `group`.
+    pub fn copy_slice_in_fused<'g, T: Copy>(
+        &self,
+        data: &[T],
+        group: &'g FusedArenaGroup,
+    ) -> Option<&'g [T]> {
+        assert!(group.contains(self), "arena is not a member of the fused group");
+        let copy = self.copy_slice_in(data)?;
+        // SAFETY: `self` is fused with `group.anchor`, so the copy lives until
+        // `group` is dropped even if `self` is dropped first.
+        Some(unsafe { &*(copy as *const [T]) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_slice_in_fused_outlives_member() {
+        let group = FusedArenaGroup::new();
+        let a = Arena::new();
+        let b = Arena::new();
+        group.join(&a);
+        group.join(&b);
+        let from_a = a.copy_slice_in_fused(&[1, 2, 3], &group).unwrap();
+        let from_b = b.copy_slice_in_fused(&[4, 5], &group).unwrap();
+        drop(a);
+        drop(b);
+        assert_eq!(from_a, &[1, 2, 3]);
+        assert_eq!(from_b, &[4, 5]);
+        assert_eq!(group.arena().copy_slice_in_fused(&[6], &group).unwrap(), &[6]);
+    }
+
+    #[gtest]
+    #[should_panic(expected = "arena is not a member of the fused group")]
+    fn test_copy_slice_in_fused_non_member() {
+        let group = FusedArenaGroup::new();
+        let arena = Arena::new();
+        arena.copy_slice_in_fused(&[1], &group);
+    }
+}
@@ -0,0 +1,81 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+/// A process-wide pool of interned strings, held in an arena which is never
+/// freed.
+///
+/// Use `intern_global` to add to the pool. This dedups strings shared across
+/// many arenas, such as schema names; since the memory is never given back,
+/// it is only meant for a bounded set of strings.
+#[derive(Debug)]
+pub struct GlobalStringPool {
+    arena: Arena,
+    strings: HashSet<&'static str>,
+}
+
+impl GlobalStringPool {
+    fn get() -> &'static Mutex<GlobalStringPool> {
+        static POOL: OnceLock<Mutex<GlobalStringPool>> = OnceLock::new();
+        POOL.get_or_init(|| {
+            Mutex::new(GlobalStringPool { arena: Arena::new(), strings: HashSet::new() })
+        })
+    }
+
+    /// Returns the number of distinct strings interned so far.
+    pub fn len() -> usize {
+        Self::get().lock().unwrap().strings.len()
+    }
+}
+
+/// Returns the canonical copy of `s` in the global string pool, copying it
+/// into the pool if it is not there yet. Returns None if the allocation
+/// failed.
+///
+/// Interning the same string from any thread returns the same reference.
+pub fn intern_global(s: &str) -> Option<&'static str> {
+    let mut pool = GlobalStringPool::get().lock().unwrap();
+    if let Some(&interned) = pool.strings.get(s) {
+        return Some(interned);
+    }
+    let copy = pool.arena.copy_str_in(s)?;
+    // SAFETY: the pool is in a static, so its arena is never dropped and the copy
+    // lives for the rest of the program.
+    let interned: &'static str = unsafe { &*(copy as *const str) };
+    pool.strings.insert(interned);
+    Some(interned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_intern_global_across_threads() {
+        let name = String::from("google.protobuf.Timestamp");
+        let from_threads: Vec<usize> = (0..2)
+            .map(|_| {
+                let name = name.clone();
+                std::thread::spawn(move || intern_global(&name).unwrap().as_ptr() as usize)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect();
+        assert_eq!(from_threads[0], from_threads[1]);
+
+        let interned = intern_global(&name).unwrap();
+        assert_eq!(interned, "google.protobuf.Timestamp");
+        assert_eq!(interned.as_ptr() as usize, from_threads[0]);
+        assert_ne!(interned.as_ptr(), name.as_ptr());
+        assert!(GlobalStringPool::len() >= 1);
+    }
+}
@@ -7,9 +7,83 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 #![cfg_attr(not(bzl), allow(unused_imports))]
 
+#[cfg(feature = "arbitrary")]
+mod arbitrary_arena_bytes;
+#[cfg(feature = "arbitrary")]
+pub use arbitrary_arena_bytes::ArbitraryArenaBytes;
+
 mod arena;
 
-pub use arena::{upb_Arena, Arena, RawArena};
+#[cfg(feature = "arena-shadow")]
+pub use arena::take_shadow_log;
+pub use arena::{
+    assert_no_alias, clear_poison_pattern, poison_pattern, set_poison_pattern, uninit_bytes_of,
+    upb_Arena, upb_alloc, Arena, ArenaError, ArenaStats, InteriorNul, LengthExceeded, RawArena,
+};
+
+mod appendable_slice;
+pub use appendable_slice::AppendableSlice;
+
+mod arena_arena;
+pub use arena_arena::ArenaArena;
+
+mod arena_bitset;
+pub use arena_bitset::ArenaBitset;
+
+mod arena_chunked_bytes;
+pub use arena_chunked_bytes::ArenaChunkedBytes;
+
+mod arena_decimals;
+pub use arena_decimals::ArenaDecimals;
+
+mod arena_delta_ints;
+pub use arena_delta_ints::ArenaDeltaInts;
+
+mod arena_guard;
+pub use arena_guard::ArenaGuard;
+
+mod arena_handle;
+pub use arena_handle::ArenaHandle;
+
+mod arena_key;
+pub use arena_key::ArenaKey;
+
+mod arena_map_index;
+pub use arena_map_index::ArenaMapIndex;
+
+mod arena_matrix;
+pub use arena_matrix::ArenaMatrix;
+
+mod arena_ptr;
+pub use arena_ptr::ArenaPtr;
+
+mod arena_region;
+
+mod arena_ring_buffer;
+pub use arena_ring_buffer::ArenaRingBuffer;
+
+mod arena_rle;
+pub use arena_rle::ArenaRle;
+
+mod arena_sorted_set;
+pub use arena_sorted_set::ArenaSortedSet;
+
+#[cfg(feature = "futures")]
+mod arena_stream;
+#[cfg(feature = "futures")]
+pub use arena_stream::StreamLengthMismatch;
+
+mod arena_string;
+pub use arena_string::ArenaString;
+
+mod arena_suffix_pool;
+pub use arena_suffix_pool::{ArenaSpan, ArenaSuffixPool};
+
+mod arena_var_records;
+pub use arena_var_records::ArenaVarRecords;
+
+mod arena_verified_bytes;
+pub use arena_verified_bytes::ArenaVerifiedBytes;
 
 mod array;
 pub use array::{
@@ -21,12 +95,21 @@ pub use array::{
 mod associated_mini_table;
 pub use associated_mini_table::AssociatedMiniTable;
 
+mod cow_arena;
+pub use cow_arena::CowArena;
+
 mod ctype;
 pub use ctype::CType;
 
 mod extension_registry;
 pub use extension_registry::{upb_ExtensionRegistry, RawExtensionRegistry};
 
+mod fused_arena_group;
+pub use fused_arena_group::FusedArenaGroup;
+
+mod global_string_pool;
+pub use global_string_pool::{intern_global, GlobalStringPool};
+
 mod map;
 pub use map::{
     upb_Map, upb_Map_Clear, upb_Map_Delete, upb_Map_Get, upb_Map_Insert, upb_Map_New, upb_Map_Next,
@@ -41,8 +124,9 @@ pub use message_value::{upb_MessageValue, upb_MutableMessageValue};
 
 mod mini_table;
 pub use mini_table::{
-    upb_MiniTable, upb_MiniTableField, upb_MiniTable_FindFieldByNumber,
-    upb_MiniTable_GetFieldByIndex, upb_MiniTable_SubMessage, RawMiniTable, RawMiniTableField,
+    upb_MiniTable, upb_MiniTableField, upb_MiniTable_Build, upb_MiniTable_FindFieldByNumber,
+    upb_MiniTable_GetFieldByIndex, upb_MiniTable_SubMessage, upb_Status, RawMiniTable,
+    RawMiniTableField,
 };
 
 mod opaque_pointee;
@@ -50,12 +134,39 @@ mod opaque_pointee;
 mod owned_arena_box;
 pub use owned_arena_box::OwnedArenaBox;
 
+mod persistable_arena;
+pub use persistable_arena::PersistableArena;
+
+mod pod;
+pub use pod::{as_bytes, as_slice_of, Pod};
+
+mod resettable_arena;
+pub use resettable_arena::ResettableArena;
+
+mod round_robin_arenas;
+pub use round_robin_arenas::RoundRobinArenas;
+
+mod scoped_arena;
+pub use scoped_arena::ScopedArena;
+
+mod shared_arena;
+pub use shared_arena::SharedArena;
+
+mod shrink_token;
+pub use shrink_token::ShrinkToken;
+
+mod size_class_arena;
+pub use size_class_arena::SizeClassArena;
+
 mod string_view;
 pub use string_view::StringView;
 
 mod text;
 pub use text::debug_string;
 
+mod typed_slab;
+pub use typed_slab::{SlabKey, TypedSlab};
+
 pub mod wire;
 pub use wire::{upb_Decode, DecodeStatus, EncodeStatus};
 
@@ -6,6 +6,7 @@
 // https://developers.google.com/open-source/licenses/bsd
 
 use super::opaque_pointee::opaque_pointee;
+use super::RawArena;
 use core::ptr::NonNull;
 
 opaque_pointee!(upb_MiniTable);
@@ -14,7 +15,23 @@ pub type RawMiniTable = NonNull<upb_MiniTable>;
 opaque_pointee!(upb_MiniTableField);
 pub type RawMiniTableField = NonNull<upb_MiniTableField>;
 
+opaque_pointee!(upb_Status);
+
 extern "C" {
+    /// Builds a MiniTable from a mini descriptor, allocating it in `arena`.
+    /// Returns NULL if the mini descriptor is invalid.
+    ///
+    /// # Safety
+    /// - `data` must be legal to read for `len` bytes
+    /// - `arena` must be valid to deref
+    /// - `status` must be either null or point to a valid upb_Status
+    pub fn upb_MiniTable_Build(
+        data: *const u8,
+        len: usize,
+        arena: RawArena,
+        status: *mut upb_Status,
+    ) -> *mut upb_MiniTable;
+
     /// Finds the field with the provided number, will return NULL if no such
     /// field is found.
     ///
@@ -55,6 +72,7 @@ mod tests {
     #[gtest]
     fn assert_mini_table_linked() {
         use super::super::assert_linked;
+        assert_linked!(upb_MiniTable_Build);
         assert_linked!(upb_MiniTable_FindFieldByNumber);
         assert_linked!(upb_MiniTable_GetFieldByIndex);
         assert_linked!(upb_MiniTable_SubMessage);
@@ -0,0 +1,88 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::{Arena, ArenaHandle};
+use core::cell::RefCell;
+
+/// An owned upb Arena of byte blobs which are identified by stable IDs, so
+/// that the arena can be saved and restored with the IDs still resolving.
+///
+/// The ID of a blob is the order it was stored in, which `to_bytes` keeps;
+/// the serialized form is the one used by `Arena::serialize_region`.
+#[derive(Debug, Default)]
+pub struct PersistableArena {
+    arena: Arena,
+    blobs: RefCell<Vec<ArenaHandle<u8>>>,
+}
+
+impl PersistableArena {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies `bytes` into the arena and returns its ID. Returns None if the
+    /// allocation failed.
+    pub fn store(&self, bytes: &[u8]) -> Option<u64> {
+        let handle = self.arena.copy_slice_in_indexed(bytes)?;
+        let mut blobs = self.blobs.borrow_mut();
+        blobs.push(handle);
+        Some(blobs.len() as u64 - 1)
+    }
+
+    /// Returns the blob stored with `id`, or None if there is none.
+    pub fn get(&self, id: u64) -> Option<&[u8]> {
+        let handle = *self.blobs.borrow().get(usize::try_from(id).ok()?)?;
+        Some(self.arena.resolve(handle))
+    }
+
+    /// Serializes every blob, in ID order, into a buffer which holds no
+    /// pointers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let blobs = self.blobs.borrow();
+        let roots: Vec<&[u8]> = blobs.iter().map(|&handle| self.arena.resolve(handle)).collect();
+        self.arena.serialize_region(&roots)
+    }
+
+    /// Rebuilds an arena from a buffer made by `to_bytes`, in which every ID
+    /// resolves to the same blob as before. Returns None if `bytes` is
+    /// malformed or if the allocation failed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let arena = Arena::new();
+        let slices = Arena::deserialize_region(&arena, bytes)?;
+        // SAFETY: `deserialize_region` returns slices of data copied into `arena`.
+        let blobs = slices.into_iter().map(|s| unsafe { arena.handle_of(s) }).collect();
+        Some(Self { arena, blobs: RefCell::new(blobs) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_ids_resolve_after_round_trip() {
+        let original = PersistableArena::new();
+        let header = original.store(b"header").unwrap();
+        let body = original.store(&[0, 1, 2, 255]).unwrap();
+        assert_ne!(header, body);
+
+        let restored = PersistableArena::from_bytes(&original.to_bytes()).unwrap();
+        drop(original);
+        assert_eq!(restored.get(header), Some(&b"header"[..]));
+        assert_eq!(restored.get(body), Some(&[0, 1, 2, 255][..]));
+        assert_eq!(restored.get(body + 1), None);
+        let added = restored.store(b"more").unwrap();
+        assert_eq!(restored.get(added), Some(&b"more"[..]));
+    }
+
+    #[gtest]
+    fn test_from_bytes_malformed() {
+        assert!(PersistableArena::from_bytes(&[1, 2, 3]).is_none());
+    }
+}
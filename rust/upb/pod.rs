@@ -0,0 +1,143 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::arena::AssertNoDropGlue;
+use super::Arena;
+use core::mem::{align_of, size_of, size_of_val};
+use core::ptr;
+use core::slice;
+
+/// Marker for "plain old data" types, which can be safely reinterpreted from
+/// arbitrary bytes.
+///
+/// # Safety
+/// - Every bit pattern of `size_of::<Self>()` bytes must be a valid `Self`.
+/// - `Self` must not contain any padding.
+///
+/// The arena never runs destructors, so types with drop glue are rejected at
+/// build time by the `Copy` supertrait, which no type with drop glue can
+/// implement:
+///
+/// ```compile_fail
+/// struct Owned(String);
+/// unsafe impl upb::Pod for Owned {}
+/// let arena = upb::Arena::new();
+/// arena.copy_bytes_as_in::<Owned>(&[0; 24]);
+/// ```
+///
+/// The copy functions bounded on `Pod` also assert at build time that the
+/// type has no drop glue, which only matters should the `Copy` bound ever be
+/// relaxed.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(
+            // SAFETY: every bit pattern is a valid value of a primitive integer or
+            // float, and they have no padding.
+            unsafe impl Pod for $t {}
+        )*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+// SAFETY: an array of `Pod` is `Pod`, since arrays have no padding between
+// elements.
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Returns the bytes of `slice`, e.g. of data copied into an arena, for
+/// generic serialization.
+///
+/// The bound is `Pod` rather than `Copy` because padding bytes are
+/// uninitialized and must not be read as `u8`.
+pub fn as_bytes<T: Pod>(slice: &[T]) -> &[u8] {
+    // SAFETY: `slice` is valid for reads of `size_of_val(slice)` bytes, all of
+    // which are initialized since `T` has no padding, and `u8` has no alignment.
+    unsafe { slice::from_raw_parts(slice.as_ptr().cast(), size_of_val(slice)) }
+}
+
+/// Reinterprets `bytes` as a slice of `T` without copying, the inverse of
+/// `as_bytes`.
+///
+/// Returns None if `bytes` is not aligned for `T` or its length is not a
+/// multiple of `size_of::<T>()`. Panics if `T` is zero-sized.
+pub fn as_slice_of<T: Pod>(bytes: &[u8]) -> Option<&[T]> {
+    assert!(size_of::<T>() != 0, "can not reinterpret bytes as a zero-sized type");
+    let (len, rem) = (bytes.len() / size_of::<T>(), bytes.len() % size_of::<T>());
+    if rem != 0 || bytes.as_ptr().align_offset(align_of::<T>()) != 0 {
+        return None;
+    }
+    // SAFETY:
+    // - `bytes` is valid for `len` elements of `T` and is aligned for `T`.
+    // - every bit pattern is a valid `T`, since `T: Pod`.
+    Some(unsafe { slice::from_raw_parts(bytes.as_ptr().cast(), len) })
+}
+
+impl Arena {
+    /// Copies the bytes into this arena, aligned for `T`, and reinterprets the
+    /// copy as a slice of `T`.
+    ///
+    /// Returns None if `bytes.len()` is not a multiple of `size_of::<T>()`, or
+    /// if the allocation failed. Panics if `T` is zero-sized.
+    pub fn copy_bytes_as_in<'a, T: Pod>(&'a self, bytes: &[u8]) -> Option<&'a [T]> {
+        #[allow(clippy::let_unit_value)]
+        let () = AssertNoDropGlue::<T>::OK;
+        assert!(size_of::<T>() != 0, "can not reinterpret bytes as a zero-sized type");
+        let (len, rem) = (bytes.len() / size_of::<T>(), bytes.len() % size_of::<T>());
+        if rem != 0 {
+            return None;
+        }
+        let alloc = self.alloc_aligned(bytes.len(), align_of::<T>())?;
+        // SAFETY:
+        // - `alloc` is valid for `bytes.len()` bytes and is aligned for `T`.
+        // - every bit pattern is a valid `T`, since `T: Pod`.
+        unsafe {
+            let alloc = alloc.as_mut_ptr().cast::<u8>();
+            ptr::copy_nonoverlapping(bytes.as_ptr(), alloc, bytes.len());
+            Some(slice::from_raw_parts(alloc.cast::<T>(), len))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_bytes_as_in() {
+        let arena = Arena::new();
+        let bytes: Vec<u8> = (1..=4u32).flat_map(u32::to_ne_bytes).collect();
+        // Misalign the source to make sure the copy is what provides alignment.
+        let mut misaligned = vec![0u8];
+        misaligned.extend_from_slice(&bytes);
+
+        let words = arena.copy_bytes_as_in::<u32>(&misaligned[1..]).unwrap();
+        assert_eq!(words, &[1, 2, 3, 4]);
+        assert_eq!(words.as_ptr() as usize % align_of::<u32>(), 0);
+    }
+
+    #[gtest]
+    fn test_copy_bytes_as_in_bad_length() {
+        let arena = Arena::new();
+        assert_eq!(arena.copy_bytes_as_in::<u32>(&[0; 6]), None);
+        assert_eq!(arena.copy_bytes_as_in::<u128>(&[0; 32]).unwrap(), &[0, 0]);
+    }
+
+    #[gtest]
+    fn test_as_bytes_round_trip() {
+        let arena = Arena::new();
+        let words = arena.copy_slice_in(&[1u32, 0x0102_0304, u32::MAX]).unwrap();
+        let bytes = as_bytes(words);
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(&bytes[4..8], &0x0102_0304u32.to_ne_bytes());
+        assert_eq!(as_slice_of::<u32>(bytes).unwrap(), words);
+        assert_eq!(as_slice_of::<u32>(&bytes[1..5]), None);
+        assert_eq!(as_slice_of::<u32>(&bytes[..6]), None);
+    }
+}
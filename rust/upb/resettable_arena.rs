@@ -0,0 +1,274 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::arena::UPB_MALLOC_ALIGN;
+use core::cell::{Cell, UnsafeCell};
+use core::mem::{align_of_val, size_of, size_of_val, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::slice;
+
+/// Size of the first block allocated by a `ResettableArena`.
+const INITIAL_BLOCK_SIZE: usize = 4096;
+
+/// The unit blocks are allocated in, so that every block starts on a
+/// `UPB_MALLOC_ALIGN` boundary.
+#[derive(Clone, Copy)]
+#[repr(C, align(8))]
+struct Chunk([MaybeUninit<u8>; UPB_MALLOC_ALIGN]);
+
+const _CHECK_CHUNK_IS_MALLOC_ALIGNED: () =
+    assert!(core::mem::align_of::<Chunk>() == UPB_MALLOC_ALIGN);
+
+/// A bump allocator over a pool of blocks owned by Rust rather than by upb.
+///
+/// Unlike `Arena`, this can be rewound with `reset()`, which makes all of its
+/// blocks available for reuse without returning them to the global allocator.
+/// Since `reset()` takes `&mut self`, the borrow checker guarantees that no
+/// references into the arena outlive it:
+///
+/// ```compile_fail
+/// let mut arena = upb::ResettableArena::new();
+/// let data = arena.copy_str_in("hello").unwrap();
+/// arena.reset();
+/// assert_eq!(data, "hello");
+/// ```
+///
+/// The allocation functions mirror those on `Arena`, but the memory is not
+/// visible to upb: it can not be used to back upb messages, and can not be
+/// fused with an `Arena`.
+pub struct ResettableArena {
+    // Leaked `Box<[Chunk]>`s, which are freed when `self` is dropped. These
+    // are held as raw pointers rather than boxes so that pushing more blocks
+    // (which moves the existing elements) does not invalidate outstanding
+    // references into them.
+    blocks: UnsafeCell<Vec<NonNull<[Chunk]>>>,
+    // Index into `blocks` of the block currently being bumped into.
+    current: Cell<usize>,
+    // Offset in bytes of the next free byte in the current block.
+    offset: Cell<usize>,
+    // Bytes allocated since the last reset, and the most seen in any cycle.
+    used: Cell<usize>,
+    high_water_mark: Cell<usize>,
+}
+
+// SAFETY: `ResettableArena` uniquely owns its blocks and has no thread-local
+// data.
+unsafe impl Send for ResettableArena {}
+
+impl ResettableArena {
+    /// Creates an arena with no blocks; the first is allocated lazily.
+    pub fn new() -> Self {
+        Self {
+            blocks: UnsafeCell::new(Vec::new()),
+            current: Cell::new(0),
+            offset: Cell::new(0),
+            used: Cell::new(0),
+            high_water_mark: Cell::new(0),
+        }
+    }
+
+    /// Rewinds the arena so that subsequent allocations reuse its existing
+    /// blocks from the start.
+    pub fn reset(&mut self) {
+        self.current.set(0);
+        self.offset.set(0);
+        self.used.set(0);
+    }
+
+    /// Returns the most bytes allocated between two resets (or since the
+    /// last one), including the rounding of each allocation to
+    /// `UPB_MALLOC_ALIGN`.
+    ///
+    /// This is the peak usage to size blocks for; unlike `capacity()`, it does
+    /// not count the space skipped at the end of a block which an allocation
+    /// did not fit in.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark.get()
+    }
+
+    /// Returns the total number of bytes in the blocks owned by this arena.
+    pub fn capacity(&self) -> usize {
+        // SAFETY: no `&mut` to `blocks` is live outside of `alloc_ptr`.
+        let blocks = unsafe { &*self.blocks.get() };
+        blocks.iter().map(|b| b.len() * size_of::<Chunk>()).sum()
+    }
+
+    /// Allocates `size` bytes aligned to `UPB_MALLOC_ALIGN`. The memory is
+    /// valid until `self` is reset or dropped.
+    fn alloc_ptr(&self, size: usize) -> *mut u8 {
+        let size = size.checked_next_multiple_of(UPB_MALLOC_ALIGN).expect("allocation too large");
+        // SAFETY: no other reference to `blocks` is live while this one is; the
+        // returned pointer points into a block, not into the Vec itself.
+        let blocks = unsafe { &mut *self.blocks.get() };
+        let mut current = self.current.get();
+        let mut offset = self.offset.get();
+        loop {
+            let Some(block) = blocks.get(current) else {
+                let last_len = blocks.last().map_or(0, |b| b.len() * size_of::<Chunk>());
+                let len = size.max(last_len * 2).max(INITIAL_BLOCK_SIZE);
+                let chunk = Chunk([MaybeUninit::uninit(); UPB_MALLOC_ALIGN]);
+                let block = vec![chunk; len.div_ceil(size_of::<Chunk>())].into_boxed_slice();
+                blocks.push(NonNull::from(Box::leak(block)));
+                offset = 0;
+                continue;
+            };
+            if block.len() * size_of::<Chunk>() - offset >= size {
+                self.current.set(current);
+                self.offset.set(offset + size);
+                self.used.set(self.used.get() + size);
+                self.high_water_mark.set(self.high_water_mark.get().max(self.used.get()));
+                // SAFETY: `offset + size` is within `block`.
+                return unsafe { block.as_ptr().cast::<u8>().add(offset) };
+            }
+            current += 1;
+            offset = 0;
+        }
+    }
+
+    /// Allocates some memory on the arena.
+    ///
+    /// This never fails, the `Option` is for parity with `Arena::alloc`.
+    ///
+    /// # Safety
+    ///
+    /// - `align` must be less than `UPB_MALLOC_ALIGN`.
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    pub unsafe fn alloc(&self, size: usize, align: usize) -> Option<&mut [MaybeUninit<u8>]> {
+        debug_assert!(align <= UPB_MALLOC_ALIGN);
+        // SAFETY: `alloc_ptr` returns a pointer valid for `size` bytes until `self`
+        // is reset or dropped, which the borrow of `self` outlives.
+        Some(unsafe { slice::from_raw_parts_mut(self.alloc_ptr(size).cast(), size) })
+    }
+
+    /// Same as alloc() but panics if `align > UPB_MALLOC_ALIGN`.
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    pub fn checked_alloc(&self, size: usize, align: usize) -> Option<&mut [MaybeUninit<u8>]> {
+        assert!(align <= UPB_MALLOC_ALIGN);
+        // SAFETY: align <= UPB_MALLOC_ALIGN asserted.
+        unsafe { self.alloc(size, align) }
+    }
+
+    /// Copies the T into this arena and returns a pointer to the T data inside
+    /// the arena.
+    pub fn copy_in<'a, T: Copy>(&'a self, data: &T) -> Option<&'a T> {
+        self.copy_slice_in(slice::from_ref(data)).map(|s| &s[0])
+    }
+
+    /// Copies the str into this arena and returns a pointer to the T data
+    /// inside the arena.
+    pub fn copy_str_in<'a>(&'a self, s: &str) -> Option<&'a str> {
+        self.copy_slice_in(s.as_bytes()).map(|copied_bytes| {
+            // SAFETY: `copied_bytes` has same contents as `s` and so must meet &str
+            // criteria.
+            unsafe { core::str::from_utf8_unchecked(copied_bytes) }
+        })
+    }
+
+    /// Copies the slice into this arena and returns a pointer to the T data
+    /// inside the arena.
+    pub fn copy_slice_in<'a, T: Copy>(&'a self, data: &[T]) -> Option<&'a [T]> {
+        assert!(align_of_val(data) <= UPB_MALLOC_ALIGN);
+        let alloc = self.alloc_ptr(size_of_val(data)).cast::<T>();
+        // SAFETY:
+        // - `alloc` is valid for `size_of_val(data)` bytes and aligned for `T`.
+        // - T is copy so copying the bytes of the values is sound.
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), alloc, data.len());
+            Some(slice::from_raw_parts(alloc, data.len()))
+        }
+    }
+}
+
+impl Drop for ResettableArena {
+    fn drop(&mut self) {
+        for block in self.blocks.get_mut().drain(..) {
+            // SAFETY: `block` was leaked from a `Box` and is not referenced anymore
+            // since `self` is being dropped.
+            drop(unsafe { Box::from_raw(block.as_ptr()) });
+        }
+    }
+}
+
+impl Default for ResettableArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for ResettableArena {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ResettableArena").field("capacity", &self.capacity()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_in_and_reset() {
+        let mut arena = ResettableArena::new();
+        let first = arena.copy_slice_in(&[1u32, 2, 3]).unwrap();
+        assert_eq!(first, &[1, 2, 3]);
+        assert_eq!(arena.copy_str_in("hello").unwrap(), "hello");
+        let first_ptr = first.as_ptr();
+        let capacity = arena.capacity();
+
+        arena.reset();
+
+        let second = arena.copy_slice_in(&[4u32, 5, 6]).unwrap();
+        assert_eq!(second, &[4, 5, 6]);
+        assert_eq!(second.as_ptr(), first_ptr);
+        assert_eq!(arena.capacity(), capacity);
+    }
+
+    #[gtest]
+    fn test_reuses_all_blocks_after_reset() {
+        let mut arena = ResettableArena::new();
+        for _ in 0..3 {
+            for i in 0..1000u64 {
+                assert_eq!(*arena.copy_in(&i).unwrap(), i);
+            }
+            arena.checked_alloc(INITIAL_BLOCK_SIZE * 4, 8).unwrap();
+            let capacity = arena.capacity();
+            arena.reset();
+            for i in 0..1000u64 {
+                arena.copy_in(&i).unwrap();
+            }
+            arena.checked_alloc(INITIAL_BLOCK_SIZE * 4, 8).unwrap();
+            assert_eq!(arena.capacity(), capacity);
+            arena.reset();
+        }
+    }
+
+    #[gtest]
+    fn test_alloc_is_malloc_aligned() {
+        let arena = ResettableArena::new();
+        arena.copy_slice_in(b"abc").unwrap();
+        let value = arena.copy_in(&7u64).unwrap();
+        assert_eq!((value as *const u64 as usize) % UPB_MALLOC_ALIGN, 0);
+    }
+
+    #[gtest]
+    fn test_high_water_mark_across_resets() {
+        let mut arena = ResettableArena::new();
+        assert_eq!(arena.high_water_mark(), 0);
+        arena.checked_alloc(10_000, 1).unwrap();
+        arena.copy_slice_in(&[0u8; 3]).unwrap();
+        assert_eq!(arena.high_water_mark(), 10_008);
+        arena.reset();
+
+        arena.checked_alloc(100, 1).unwrap();
+        assert_eq!(arena.high_water_mark(), 10_008);
+        arena.reset();
+        arena.checked_alloc(20_000, 1).unwrap();
+        assert_eq!(arena.high_water_mark(), 20_000);
+    }
+}
@@ -0,0 +1,128 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::ops::Deref;
+
+/// An `Arena` tagged with a label, for correlating arena memory with the
+/// request flow that created it.
+///
+/// When the `tracing` feature is enabled, dropping a `ScopedArena` emits a
+/// `tracing` event carrying the label and the final `space_allocated()` of the
+/// arena. Otherwise this is a zero-cost wrapper over `Arena`.
+#[derive(Debug)]
+pub struct ScopedArena {
+    arena: Arena,
+    label: &'static str,
+}
+
+impl Arena {
+    /// Allocates a fresh arena that reports its lifetime under `label` when
+    /// dropped. See `ScopedArena`.
+    pub fn scoped(label: &'static str) -> ScopedArena {
+        ScopedArena { arena: Arena::new(), label }
+    }
+}
+
+impl ScopedArena {
+    /// The label this arena was created with.
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+}
+
+impl Deref for ScopedArena {
+    type Target = Arena;
+    fn deref(&self) -> &Arena {
+        &self.arena
+    }
+}
+
+impl Drop for ScopedArena {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "upb::arena",
+            label = self.label,
+            space_allocated = self.arena.space_allocated(),
+            "arena dropped"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_scoped_arena_allocates() {
+        let arena = Arena::scoped("test");
+        assert_eq!(arena.label(), "test");
+        assert_eq!(arena.copy_str_in("hello").unwrap(), "hello");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[gtest]
+    fn test_scoped_arena_drop_event() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Default)]
+        struct Captured {
+            label: Option<String>,
+            space_allocated: Option<u64>,
+        }
+
+        impl Visit for Captured {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                if field.name() == "label" {
+                    self.label = Some(value.to_owned());
+                }
+            }
+            fn record_u64(&mut self, field: &Field, value: u64) {
+                if field.name() == "space_allocated" {
+                    self.space_allocated = Some(value);
+                }
+            }
+            fn record_debug(&mut self, _field: &Field, _value: &dyn core::fmt::Debug) {}
+        }
+
+        struct CapturingSubscriber(Arc<Mutex<Captured>>);
+
+        impl Subscriber for CapturingSubscriber {
+            fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+                metadata.target() == "upb::arena"
+            }
+            fn new_span(&self, _span: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _span: &Id, _values: &Record<'_>) {}
+            fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                event.record(&mut *self.0.lock().unwrap());
+            }
+            fn enter(&self, _span: &Id) {}
+            fn exit(&self, _span: &Id) {}
+        }
+
+        let captured = Arc::new(Mutex::new(Captured::default()));
+        let subscriber = CapturingSubscriber(Arc::clone(&captured));
+        let expected_space_allocated = tracing::subscriber::with_default(subscriber, || {
+            let arena = Arena::scoped("request");
+            arena.copy_slice_in(&[0u8; 1024]).unwrap();
+            arena.space_allocated()
+        });
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(captured.label.as_deref(), Some("request"));
+        assert_eq!(captured.space_allocated, Some(expected_space_allocated as u64));
+    }
+}
@@ -0,0 +1,154 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::{Arena, RawArena};
+
+/// A reference-counted handle which keeps the memory of a upb Arena (and any
+/// arenas fused to it) alive.
+///
+/// A `SharedArena` can not allocate, it only extends the lifetime of memory
+/// that was already allocated. That makes it safe to send and share across
+/// threads, unlike `Arena`, since upb maintains the refcount atomically.
+#[derive(Debug)]
+pub struct SharedArena {
+    // Safety invariant: this is a valid arena on which this handle holds one
+    // reference.
+    raw: RawArena,
+}
+
+// SAFETY: `SharedArena` only touches the arena through the atomic refcount.
+unsafe impl Send for SharedArena {}
+// SAFETY: `SharedArena` has no `&self` methods which touch the arena other than
+// through the atomic refcount.
+unsafe impl Sync for SharedArena {}
+
+impl Arena {
+    /// Returns a new reference-counted handle to this arena's memory.
+    ///
+    /// Panics if the arena can not be refcounted, which is the case for
+    /// arenas backed by an initial block that they don't own.
+    pub fn share(&self) -> SharedArena {
+        // SAFETY: `self.raw()` is a valid UPB arena.
+        let success = unsafe { upb_Arena_IncRefFor(self.raw(), core::ptr::null()) };
+        if !success {
+            panic!("Could not add a reference to a UPB arena.");
+        }
+        SharedArena { raw: self.raw() }
+    }
+}
+
+impl SharedArena {
+    /// Returns the raw, UPB-managed pointer to the arena.
+    ///
+    /// The pointer must not be used to allocate unless the caller otherwise
+    /// knows that no other thread is allocating on the arena.
+    pub fn raw(&self) -> RawArena {
+        self.raw
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl SharedArena {
+    /// Wraps arena-owned bytes as a `bytes::Bytes` without copying them.
+    ///
+    /// The `Bytes` (and any clones or slices of it) hold `self`, so the arena
+    /// memory stays alive for as long as they do.
+    ///
+    /// # Safety
+    /// - `slice` must point into memory allocated on the arena `self` refers to
+    ///   (or an arena fused to it), and must not be mutated while the returned
+    ///   `Bytes` is live.
+    pub unsafe fn into_bytes(self, slice: &[u8]) -> bytes::Bytes {
+        struct ArenaBytesOwner {
+            data: *const u8,
+            len: usize,
+            _arena: SharedArena,
+        }
+
+        // SAFETY: the bytes are immutable and kept alive by the `SharedArena`,
+        // which is itself `Send`.
+        unsafe impl Send for ArenaBytesOwner {}
+
+        impl AsRef<[u8]> for ArenaBytesOwner {
+            fn as_ref(&self) -> &[u8] {
+                // SAFETY: `data` is valid for `len` bytes while `_arena` is live, as
+                // promised by the caller of `into_bytes`.
+                unsafe { core::slice::from_raw_parts(self.data, self.len) }
+            }
+        }
+
+        bytes::Bytes::from_owner(ArenaBytesOwner {
+            data: slice.as_ptr(),
+            len: slice.len(),
+            _arena: self,
+        })
+    }
+}
+
+impl Clone for SharedArena {
+    fn clone(&self) -> Self {
+        // SAFETY: `self.raw` is a valid arena, which was already successfully
+        // refcounted so can be again.
+        let success = unsafe { upb_Arena_IncRefFor(self.raw, core::ptr::null()) };
+        assert!(success);
+        SharedArena { raw: self.raw }
+    }
+}
+
+impl Drop for SharedArena {
+    fn drop(&mut self) {
+        // SAFETY: `self` holds a reference on `self.raw`.
+        unsafe { upb_Arena_DecRefFor(self.raw, core::ptr::null()) }
+    }
+}
+
+extern "C" {
+    fn upb_Arena_IncRefFor(arena: RawArena, owner: *const core::ffi::c_void) -> bool;
+    fn upb_Arena_DecRefFor(arena: RawArena, owner: *const core::ffi::c_void);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn assert_shared_arena_linked() {
+        use super::super::assert_linked;
+        assert_linked!(upb_Arena_IncRefFor);
+        assert_linked!(upb_Arena_DecRefFor);
+    }
+
+    #[gtest]
+    fn test_shared_arena_outlives_arena() {
+        let arena = Arena::new();
+        // SAFETY: `data` is only read while `shared2` is live.
+        let data: &'static str = unsafe { &*(arena.copy_str_in("hello").unwrap() as *const str) };
+        let shared = arena.share();
+        let shared2 = shared.clone();
+        drop(arena);
+        drop(shared);
+        let from_thread = std::thread::spawn(move || {
+            let s = data.to_owned();
+            drop(shared2);
+            s
+        });
+        assert_eq!(from_thread.join().unwrap(), "hello");
+    }
+
+    #[cfg(feature = "bytes")]
+    #[gtest]
+    fn test_into_bytes_keeps_arena_alive() {
+        let arena = Arena::new();
+        let data = arena.copy_slice_in(b"payload").unwrap();
+        // SAFETY: `data` was allocated on `arena` and is never mutated.
+        let bytes = unsafe { arena.share().into_bytes(data) };
+        drop(arena);
+        assert_eq!(&bytes[..], b"payload");
+        assert_eq!(&bytes.slice(3..)[..], b"load");
+    }
+}
@@ -0,0 +1,105 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::{Arena, RawArena};
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+/// An allocation from a upb Arena which can later be shrunk with
+/// `Arena::apply_shrink`, a safe wrapper over `Arena::shrink_last`.
+///
+/// The allocated bytes are only reachable through the token, so once it is
+/// shrunk the caller can not touch the bytes given back to the arena.
+#[derive(Debug)]
+pub struct ShrinkToken<'a> {
+    data: &'a mut [MaybeUninit<u8>],
+    arena: RawArena,
+}
+
+impl Arena {
+    /// Allocates `size` bytes which can later be shrunk with `apply_shrink`.
+    /// Returns None if the allocation failed.
+    pub fn alloc_shrinkable(&self, size: usize) -> Option<ShrinkToken<'_>> {
+        let data = self.checked_alloc(size, 1)?;
+        Some(ShrinkToken { data, arena: self.raw() })
+    }
+
+    /// Shrinks the allocation of `token` to `new_size` bytes and returns the
+    /// remaining prefix.
+    ///
+    /// The tail is returned to the arena if the allocation is still the most
+    /// recent one, which upb checks; otherwise it stays unused until the arena
+    /// is freed.
+    ///
+    /// Panics if `new_size` is larger than the allocation or if `token` was
+    /// allocated from another arena.
+    pub fn apply_shrink<'a>(
+        &self,
+        token: ShrinkToken<'a>,
+        new_size: usize,
+    ) -> &'a mut [MaybeUninit<u8>] {
+        assert_eq!(token.arena, self.raw(), "token is from a different arena");
+        let ShrinkToken { data, .. } = token;
+        let old_size = data.len();
+        assert!(new_size <= old_size, "can not grow an allocation with apply_shrink");
+        // SAFETY:
+        // - `data` is an allocation of `old_size` bytes from this arena.
+        // - the bytes past `new_size` are not reachable anymore, since `data` is
+        //   consumed and only the prefix is returned.
+        unsafe { self.shrink_last(NonNull::from(&mut *data).cast(), old_size, new_size) };
+        &mut data[..new_size]
+    }
+}
+
+impl Deref for ShrinkToken<'_> {
+    type Target = [MaybeUninit<u8>];
+    fn deref(&self) -> &[MaybeUninit<u8>] {
+        self.data
+    }
+}
+
+impl DerefMut for ShrinkToken<'_> {
+    fn deref_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_apply_shrink() {
+        let arena = Arena::new();
+        let mut token = arena.alloc_shrinkable(4000).unwrap();
+        for (i, b) in token[..10].iter_mut().enumerate() {
+            b.write(i as u8);
+        }
+        let prefix = arena.apply_shrink(token, 10);
+        assert_eq!(prefix.len(), 10);
+        // SAFETY: the prefix was initialized above.
+        assert_eq!(
+            unsafe { &*(prefix as *const [MaybeUninit<u8>] as *const [u8]) },
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+
+        // The tail was given back, so it is reused without growing the arena.
+        let space_allocated = arena.space_allocated();
+        arena.copy_slice_in(&[0u8; 3000]).unwrap();
+        assert_eq!(arena.space_allocated(), space_allocated);
+    }
+
+    #[gtest]
+    #[should_panic(expected = "token is from a different arena")]
+    fn test_apply_shrink_wrong_arena() {
+        let (a, b) = (Arena::new(), Arena::new());
+        let token = a.alloc_shrinkable(16).unwrap();
+        b.apply_shrink(token, 8);
+    }
+}
@@ -0,0 +1,106 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::cell::RefCell;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+use core::slice;
+
+/// The size classes served from free lists, smallest first.
+const SIZE_CLASSES: [usize; 4] = [8, 16, 32, 64];
+
+/// A front-end over a upb Arena which reuses returned allocations of common
+/// sizes.
+///
+/// Allocations of up to 64 bytes are rounded up to a size class of 8, 16, 32
+/// or 64 bytes, and slots handed back with `recycle` are reused by later
+/// allocations of the same class. The arena never frees, so recycled slots
+/// only ever go back to this `SizeClassArena`; larger allocations go straight
+/// to the arena and can not be recycled.
+#[derive(Debug)]
+pub struct SizeClassArena<'a> {
+    arena: &'a Arena,
+    free: RefCell<[Vec<NonNull<u8>>; SIZE_CLASSES.len()]>,
+}
+
+impl Arena {
+    /// Returns a size-class allocator on top of this arena.
+    pub fn size_classes(&self) -> SizeClassArena<'_> {
+        SizeClassArena { arena: self, free: RefCell::default() }
+    }
+}
+
+impl<'a> SizeClassArena<'a> {
+    fn class_of(size: usize) -> Option<usize> {
+        SIZE_CLASSES.iter().position(|&class| size <= class)
+    }
+
+    /// Allocates `size` bytes, reusing a recycled slot of the same size class
+    /// if there is one. Returns None if the allocation failed.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, size: usize) -> Option<&'a mut [MaybeUninit<u8>]> {
+        let Some(class) = Self::class_of(size) else {
+            return self.arena.checked_alloc(size, 1);
+        };
+        let ptr = match self.free.borrow_mut()[class].pop() {
+            Some(ptr) => ptr,
+            None => NonNull::from(self.arena.checked_alloc(SIZE_CLASSES[class], 1)?).cast(),
+        };
+        // SAFETY: `ptr` is a slot of `SIZE_CLASSES[class] >= size` bytes in the arena
+        // which nothing else references: it is either fresh or was recycled, in which
+        // case the caller of `recycle` gave up all access to it.
+        Some(unsafe { slice::from_raw_parts_mut(ptr.as_ptr().cast(), size) })
+    }
+
+    /// Returns the slot at `ptr` for reuse by a later allocation of the same
+    /// size class. Slots larger than the largest size class are ignored.
+    ///
+    /// # Safety
+    /// - `ptr` must have been returned by `alloc(size)` on this
+    ///   `SizeClassArena`, and not already recycled.
+    /// - The slot must not be accessed after this call.
+    pub unsafe fn recycle(&self, ptr: *mut u8, size: usize) {
+        if let (Some(class), Some(ptr)) = (Self::class_of(size), NonNull::new(ptr)) {
+            self.free.borrow_mut()[class].push(ptr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_recycled_slot_is_reused() {
+        let arena = Arena::new();
+        let classes = arena.size_classes();
+        let first = classes.alloc(12).unwrap().as_mut_ptr().cast::<u8>();
+        let other = classes.alloc(12).unwrap().as_mut_ptr().cast::<u8>();
+        assert_ne!(first, other);
+
+        // SAFETY: `first` came from `alloc(12)` and is not used again.
+        unsafe { classes.recycle(first, 12) };
+        // 9 bytes is in the same 16 byte class; a 32 byte class slot is fresh.
+        assert_ne!(classes.alloc(20).unwrap().as_mut_ptr().cast::<u8>(), first);
+        assert_eq!(classes.alloc(9).unwrap().as_mut_ptr().cast::<u8>(), first);
+        assert_ne!(classes.alloc(12).unwrap().as_mut_ptr().cast::<u8>(), first);
+    }
+
+    #[gtest]
+    fn test_large_allocations_are_not_recycled() {
+        let arena = Arena::new();
+        let classes = arena.size_classes();
+        let large = classes.alloc(100).unwrap();
+        assert_eq!(large.len(), 100);
+        let large = large.as_mut_ptr().cast::<u8>();
+        // SAFETY: `large` came from `alloc(100)` and is not used again.
+        unsafe { classes.recycle(large, 100) };
+        assert_ne!(classes.alloc(100).unwrap().as_mut_ptr().cast::<u8>(), large);
+    }
+}
@@ -5,6 +5,12 @@
 // license that can be found in the LICENSE file or at
 // https://developers.google.com/open-source/licenses/bsd
 
+use super::Arena;
+use core::mem::{align_of, size_of};
+use core::ptr;
+use core::slice;
+use core::str::Utf8Error;
+
 /// ABI compatible struct with upb_StringView.
 ///
 /// Note that this has semantics similar to `std::string_view` in C++ and
@@ -54,3 +60,102 @@ impl<const N: usize> From<&[u8; N]> for StringView {
         Self { ptr: slice.as_ptr(), len: N }
     }
 }
+
+impl Arena {
+    /// Copies the bytes a `StringView` points at into this arena. Returns None
+    /// if the allocation failed.
+    ///
+    /// # Safety
+    /// - `view` must satisfy the safety requirements of `StringView::as_ref`
+    ///   for the duration of this call.
+    pub unsafe fn copy_string_view_in(&self, view: StringView) -> Option<&[u8]> {
+        // SAFETY: `view` is valid to dereference, as promised by the caller.
+        self.copy_slice_in(unsafe { view.as_ref() })
+    }
+
+    /// Same as copy_string_view_in() but first checks that the bytes are valid
+    /// UTF-8, returning an error without allocating if they are not.
+    ///
+    /// # Safety
+    /// - `view` must satisfy the safety requirements of `StringView::as_ref`
+    ///   for the duration of this call.
+    pub unsafe fn copy_string_view_str_in(
+        &self,
+        view: StringView,
+    ) -> Result<Option<&str>, Utf8Error> {
+        // SAFETY: `view` is valid to dereference, as promised by the caller.
+        let s = core::str::from_utf8(unsafe { view.as_ref() })?;
+        Ok(self.copy_str_in(s))
+    }
+
+    /// Copies the strings into this arena and returns an array of
+    /// `StringView`s pointing at the copies, e.g. to pass to a C API taking
+    /// `upb_StringView*`. Returns None if an allocation failed.
+    ///
+    /// The bytes of all strings are copied into one allocation and the views
+    /// into another. Empty strings get zero-length views.
+    pub fn copy_string_views_in<'a>(&'a self, strings: &[&str]) -> Option<&'a [StringView]> {
+        let total = strings.iter().try_fold(0usize, |sum, s| sum.checked_add(s.len()))?;
+        let bytes = self.checked_alloc(total, 1)?.as_mut_ptr().cast::<u8>();
+        let size = size_of::<StringView>().checked_mul(strings.len())?;
+        let views = self.checked_alloc(size, align_of::<StringView>())?;
+        let views = views.as_mut_ptr().cast::<StringView>();
+        let mut offset = 0;
+        for (i, s) in strings.iter().enumerate() {
+            // SAFETY:
+            // - `bytes` is valid for `total` bytes, the sum of the string lengths, so for
+            //   `s.len()` bytes after the preceding strings.
+            // - `views` is valid for `strings.len()` views and aligned for them.
+            unsafe {
+                let data = bytes.add(offset);
+                ptr::copy_nonoverlapping(s.as_ptr(), data, s.len());
+                views.add(i).write(StringView { ptr: data, len: s.len() });
+            }
+            offset += s.len();
+        }
+        // SAFETY: every view was written by the loop above.
+        Some(unsafe { slice::from_raw_parts(views, strings.len()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_copy_string_view_in() {
+        let arena = Arena::new();
+        let buf = b"hello".to_vec();
+        // SAFETY: `buf` is live and unmodified for the duration of each call.
+        unsafe {
+            let copy = arena.copy_string_view_in(StringView::from(&buf[..])).unwrap();
+            assert_eq!(copy, b"hello");
+            assert_ne!(copy.as_ptr(), buf.as_ptr());
+
+            let empty = StringView { ptr: core::ptr::null(), len: 0 };
+            assert_eq!(arena.copy_string_view_in(empty).unwrap(), b"");
+        }
+    }
+
+    #[gtest]
+    fn test_copy_string_views_in() {
+        let arena = Arena::new();
+        let views = arena.copy_string_views_in(&["a", "", "bcd"]).unwrap();
+        assert_eq!(views.iter().map(|v| v.len).collect::<Vec<_>>(), [1, 0, 3]);
+        // SAFETY: the views point into `arena`, which is live and not mutated.
+        let strings: Vec<&[u8]> = views.iter().map(|&v| unsafe { v.as_ref() }).collect();
+        assert_eq!(strings, [&b"a"[..], b"", b"bcd"]);
+        assert!(arena.copy_string_views_in(&[]).unwrap().is_empty());
+    }
+
+    #[gtest]
+    fn test_copy_string_view_str_in() {
+        let arena = Arena::new();
+        // SAFETY: the viewed arrays are 'static.
+        unsafe {
+            assert_eq!(arena.copy_string_view_str_in(b"hello".into()), Ok(Some("hello")));
+            assert!(arena.copy_string_view_str_in(b"\xff".into()).is_err());
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+//! Builds upb messages whose MiniTable, message and field data are all
+//! allocated through `Arena`, and round-trips them through the wire format.
+
+use googletest::gtest;
+use upb::{
+    upb_Message_GetInt32, upb_Message_GetString, upb_Message_HasBaseField, upb_Message_New,
+    upb_Message_SetBaseFieldInt32, upb_Message_SetBaseFieldString, upb_MiniTable,
+    upb_MiniTableField, upb_MiniTable_Build, upb_MiniTable_FindFieldByNumber, wire, Arena,
+    RawMessage, StringView,
+};
+
+/// Mini descriptor for a message with an `int32` field 1 and a `string`
+/// field 2.
+const MINI_DESCRIPTOR: &[u8] = b"$(1";
+
+fn build_mini_table(arena: &Arena) -> *const upb_MiniTable {
+    // SAFETY: `MINI_DESCRIPTOR` is readable for its length and `status` may be
+    // null.
+    let mini_table = unsafe {
+        upb_MiniTable_Build(
+            MINI_DESCRIPTOR.as_ptr(),
+            MINI_DESCRIPTOR.len(),
+            arena.raw(),
+            core::ptr::null_mut(),
+        )
+    };
+    assert!(!mini_table.is_null());
+    mini_table
+}
+
+fn field(mini_table: *const upb_MiniTable, number: u32) -> *const upb_MiniTableField {
+    // SAFETY: `mini_table` is a valid MiniTable.
+    let f = unsafe { upb_MiniTable_FindFieldByNumber(mini_table, number) };
+    assert!(!f.is_null());
+    f
+}
+
+fn new_message(mini_table: *const upb_MiniTable, arena: &Arena) -> RawMessage {
+    // SAFETY: `mini_table` and `arena` are valid.
+    unsafe { upb_Message_New(mini_table, arena.raw()) }.unwrap()
+}
+
+#[gtest]
+fn test_round_trip_arena_backed_message() {
+    let arena = Arena::new();
+    let mini_table = build_mini_table(&arena);
+    let (id, name) = (field(mini_table, 1), field(mini_table, 2));
+
+    let msg = new_message(mini_table, &arena);
+    assert_eq!(msg.as_ptr() as usize % 8, 0);
+    // The string field borrows its bytes, which the arena keeps alive as long
+    // as the message.
+    let name_bytes = arena.copy_str_in("arena").unwrap().as_bytes();
+    // SAFETY: `msg` is associated with `mini_table`, `id` is its int32 field and
+    // `name` its string field, and `name_bytes` outlives `msg`.
+    unsafe {
+        upb_Message_SetBaseFieldInt32(msg, id, 42);
+        upb_Message_SetBaseFieldString(msg, name, StringView::from(name_bytes));
+    }
+
+    // SAFETY: `msg` is associated with `mini_table`.
+    let bytes = unsafe { wire::encode(msg, mini_table) }.unwrap();
+    assert_eq!(bytes, b"\x08\x2a\x12\x05arena");
+
+    let parse_arena = Arena::new();
+    let parsed = new_message(mini_table, &parse_arena);
+    // SAFETY: `parsed` is mutable and associated with `mini_table`.
+    unsafe { wire::decode(&bytes, parsed, mini_table, &parse_arena) }.unwrap();
+    drop(bytes);
+
+    // SAFETY: `parsed` is associated with `mini_table`, and string data it
+    // references lives in `parse_arena`.
+    unsafe {
+        assert!(upb_Message_HasBaseField(parsed, id));
+        assert_eq!(upb_Message_GetInt32(parsed, id, 0), 42);
+        let parsed_name = upb_Message_GetString(parsed, name, StringView::from(b"")).as_ref();
+        assert_eq!(parsed_name, b"arena");
+    }
+}
+
+#[gtest]
+fn test_unset_fields_after_round_trip() {
+    let arena = Arena::new();
+    let mini_table = build_mini_table(&arena);
+    let (id, name) = (field(mini_table, 1), field(mini_table, 2));
+
+    let msg = new_message(mini_table, &arena);
+    // SAFETY: `msg` is associated with `mini_table`.
+    let bytes = unsafe { wire::encode(msg, mini_table) }.unwrap();
+    assert!(bytes.is_empty());
+
+    let parsed = new_message(mini_table, &arena);
+    // SAFETY: `parsed` is mutable and associated with `mini_table`.
+    unsafe {
+        wire::decode(&bytes, parsed, mini_table, &arena).unwrap();
+        assert!(!upb_Message_HasBaseField(parsed, id));
+        assert!(!upb_Message_HasBaseField(parsed, name));
+        assert_eq!(upb_Message_GetInt32(parsed, id, -1), -1);
+    }
+}
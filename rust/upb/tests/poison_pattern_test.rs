@@ -0,0 +1,30 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+//! Tests of the process-wide poison pattern. These live in their own test
+//! binary, and so their own process, so that turning poisoning on can not
+//! affect allocations made by tests running in parallel.
+
+// Poisoning only happens in builds with debug assertions.
+#![cfg(debug_assertions)]
+
+use googletest::gtest;
+use upb::{clear_poison_pattern, poison_pattern, set_poison_pattern, Arena};
+
+#[gtest]
+fn test_poison_pattern() {
+    // arena-deterministic zeroes every allocation after it is poisoned.
+    let expected = if cfg!(feature = "arena-deterministic") { 0 } else { 0xAB };
+    set_poison_pattern(0xAB);
+    assert_eq!(poison_pattern(), Some(0xAB));
+    let arena = Arena::new();
+    let alloc = arena.checked_alloc(24, 1).unwrap();
+    // SAFETY: the allocation was filled with the poison pattern, or zeroed.
+    assert!(alloc.iter().all(|b| unsafe { b.assume_init() } == expected));
+    clear_poison_pattern();
+    assert_eq!(poison_pattern(), None);
+}
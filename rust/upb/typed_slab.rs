@@ -0,0 +1,128 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2024 Google LLC.  All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file or at
+// https://developers.google.com/open-source/licenses/bsd
+
+use super::Arena;
+use core::slice;
+
+/// Identifies a value in a `TypedSlab`.
+///
+/// A key goes stale when its value is freed: the slot may then be reused,
+/// but the new value gets a key with a later generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SlabKey {
+    index: u32,
+    generation: u32,
+}
+
+impl SlabKey {
+    /// Returns the number of times the slot was freed before this key's
+    /// value was stored in it.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+#[derive(Debug)]
+struct Slot<'a, T> {
+    value: &'a mut T,
+    generation: u32,
+}
+
+/// A pool of `T`s allocated from an arena, which reuses the slots of freed
+/// values.
+///
+/// The arena never frees, so without reuse a workload that keeps replacing
+/// values grows the arena without bound; with it, the arena holds at most as
+/// many `T`s as were ever live at once.
+#[derive(Debug)]
+pub struct TypedSlab<'a, T> {
+    arena: &'a Arena,
+    slots: Vec<Slot<'a, T>>,
+    free: Vec<u32>,
+}
+
+impl Arena {
+    /// Returns an empty slab of `T`s allocated from this arena.
+    pub fn typed_slab<T: Copy>(&self) -> TypedSlab<'_, T> {
+        TypedSlab { arena: self, slots: Vec::new(), free: Vec::new() }
+    }
+}
+
+impl<'a, T: Copy> TypedSlab<'a, T> {
+    /// Stores `value`, in a freed slot if there is one. Returns None if the
+    /// allocation failed.
+    ///
+    /// This takes `&mut self` so that no reference returned by `get` is live
+    /// while a slot is overwritten.
+    pub fn alloc(&mut self, value: T) -> Option<SlabKey> {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            *slot.value = value;
+            return Some(SlabKey { index, generation: slot.generation });
+        }
+        let index = u32::try_from(self.slots.len()).ok()?;
+        let value = &mut self.arena.copy_slice_in_mut(slice::from_ref(&value))?[0];
+        self.slots.push(Slot { value, generation: 0 });
+        Some(SlabKey { index, generation: 0 })
+    }
+
+    /// Frees the value for `key`, so its slot can be reused by `alloc`. Stale
+    /// keys are ignored.
+    pub fn free(&mut self, key: SlabKey) {
+        if self.get(key).is_none() {
+            return;
+        }
+        let slot = &mut self.slots[key.index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+    }
+
+    /// Returns the value for `key`, or None if it was freed.
+    pub fn get(&self, key: SlabKey) -> Option<&T> {
+        let slot = self.slots.get(key.index as usize)?;
+        // Freeing bumps the slot's generation, so a freed value's key never
+        // matches.
+        (slot.generation == key.generation).then_some(&*slot.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use googletest::gtest;
+
+    #[gtest]
+    fn test_freed_slot_is_reused() {
+        let arena = Arena::new();
+        let mut slab = arena.typed_slab::<u64>();
+        let first = slab.alloc(1).unwrap();
+        let second = slab.alloc(2).unwrap();
+        let first_ptr = slab.get(first).unwrap() as *const u64;
+
+        slab.free(first);
+        assert_eq!(slab.get(first), None);
+        let reused = slab.alloc(3).unwrap();
+        assert_eq!(reused.generation(), first.generation() + 1);
+        assert_eq!(slab.get(reused), Some(&3));
+        assert_eq!(slab.get(reused).unwrap() as *const u64, first_ptr);
+        assert_eq!(slab.get(first), None);
+        assert_eq!(slab.get(second), Some(&2));
+    }
+
+    #[gtest]
+    fn test_stale_free_is_ignored() {
+        let arena = Arena::new();
+        let mut slab = arena.typed_slab::<u32>();
+        let key = slab.alloc(7).unwrap();
+        slab.free(key);
+        slab.free(key);
+        let a = slab.alloc(8).unwrap();
+        let b = slab.alloc(9).unwrap();
+        assert_ne!(a, b);
+        assert_eq!((slab.get(a), slab.get(b)), (Some(&8), Some(&9)));
+    }
+}